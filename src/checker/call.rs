@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use super::types::{accepts, iterated_types, rigid, type_name};
+use super::{Checker, Target, Variable};
+use crate::runtime::builtins::builtins;
+use crate::runtime::environment::LibFunction;
+use crate::syntax::ast::{Argument, Param};
+use crate::syntax::lexer::Type;
+
+impl Checker {
+    /// Checks a call to `name`, returning the type of its result
+    pub fn check_call(&mut self, name: &str, args: &[Argument]) -> Option<Type> {
+        let arg_types = self.check_arg_values(args);
+
+        let lib_function = match name.split_once("::") {
+            Some((module, member)) if self.files.contains_key(module) => {
+                let callee = self.export(module, member, "function")?;
+                return self.check_callee(name, callee, args, &arg_types);
+            }
+            Some((module, member)) => {
+                let function = self
+                    .modules
+                    .get(module)?
+                    .iter()
+                    .find(|f| f.name == member)
+                    .cloned();
+                if function.is_none() {
+                    let message =
+                        format!("Module '{}' has no exported function '{}'", module, member);
+                    self.throw_error(message);
+                }
+                Some(function?)
+            }
+            // built ins come before variables, like they do at runtime
+            None => builtins().into_iter().find(|f| f.name == name),
+        };
+        if let Some(function) = lib_function {
+            return self.check_lib_call(name, &function, args, &arg_types);
+        }
+
+        let callee = self.lookup(name)?.clone();
+        self.check_callee(name, callee, args, &arg_types)
+    }
+
+    /// Checks a call to the function or value in variable `name`
+    fn check_callee(
+        &mut self,
+        name: &str,
+        callee: Variable,
+        args: &[Argument],
+        arg_types: &[Option<Type>],
+    ) -> Option<Type> {
+        match (callee.params, callee.current?) {
+            (Some(params), Type::Function { ret, .. }) => {
+                self.check_params(name, &params, *ret, args, arg_types)
+            }
+            (_, ty) => self.check_value_call(name, &format!("'{}'", name), ty, args, arg_types),
+        }
+    }
+
+    /// What `member` of the imported file `module` is, reporting it when the
+    /// file doesn't export it. `what` is what it's expected to be
+    pub fn export(&mut self, module: &str, member: &str, what: &str) -> Option<Variable> {
+        let export = self.files.get(module)?.get(member).cloned();
+        if export.is_none() {
+            let message = format!("Module '{}' has no exported {} '{}'", module, what, member);
+            self.throw_error(message);
+        }
+        export
+    }
+
+    /// The type of a function provided by the interpreter used as a value,
+    /// i.e. `print` in `apply(print, 1)`
+    pub fn lib_function_value(&self, name: &str) -> Option<Type> {
+        let known = match name.split_once("::") {
+            Some((module, member)) => self.modules.get(module)?.iter().any(|f| f.name == member),
+            None => builtins().iter().any(|f| f.name == name),
+        };
+        // built ins may be variadic or generic, which a function type can't
+        // spell out
+        known.then_some(Type::AnyFunction)
+    }
+
+    /// Checks the arguments of a call on their own, returning their types
+    pub fn check_arg_values(&mut self, args: &[Argument]) -> Vec<Option<Type>> {
+        let mut arg_types = Vec::new();
+        for arg in args {
+            let ty = self.check_expr(&arg.value);
+            if arg.spread {
+                self.expect_present(&arg.value, &ty, "spread");
+            }
+            arg_types.push(ty);
+        }
+        arg_types
+    }
+
+    /// Checks a call to a value of type `ty`, like a parameter holding a
+    /// function or the result of another call. `callee` is how errors refer to
+    /// the value
+    pub fn check_value_call(
+        &mut self,
+        name: &str,
+        callee: &str,
+        ty: Type,
+        args: &[Argument],
+        arg_types: &[Option<Type>],
+    ) -> Option<Type> {
+        let (params, ret) = match ty {
+            // a value holding a function only has the types of its
+            // parameters, which take positional arguments
+            Type::Function { params, ret } => {
+                let params: Vec<Param> = params
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, ty)| Param {
+                        name: format!("#{}", index + 1),
+                        ty,
+                        default: None,
+                        variadic: false,
+                    })
+                    .collect();
+                (params, *ret)
+            }
+            Type::AnyFunction | Type::Param(_) | Type::Any => return None,
+            ty => {
+                let message = format!(
+                    "{} is not a function, it's a value of type '{}'",
+                    callee,
+                    type_name(&ty)
+                );
+                self.throw_error(message);
+                return None;
+            }
+        };
+        self.check_params(name, &params, ret, args, arg_types)
+    }
+
+    /// Checks the arguments of a call to function `name` against its
+    /// parameters, returning the type of its result
+    fn check_params(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        ret: Type,
+        args: &[Argument],
+        arg_types: &[Option<Type>],
+    ) -> Option<Type> {
+        let mut bindings = HashMap::new();
+        self.check_args(name, params, args, arg_types, &mut bindings);
+
+        // a return type that depends on type parameters the arguments didn't
+        // bind isn't known
+        let ret = ret.instantiate(&bindings);
+        match ret.contains_param() {
+            true => None,
+            false => Some(ret),
+        }
+    }
+
+    /// Checks a call to a function provided by the interpreter against its
+    /// signature, returning the type of its result
+    fn check_lib_call(
+        &mut self,
+        name: &str,
+        function: &LibFunction,
+        args: &[Argument],
+        arg_types: &[Option<Type>],
+    ) -> Option<Type> {
+        if let Some(arg) = args.iter().find(|arg| arg.name.is_some()) {
+            let message = format!(
+                "Library function '{}' doesn't take named arguments, but got '{}'",
+                name,
+                arg.name.as_deref().unwrap_or_default()
+            );
+            self.throw_error(message);
+            return None;
+        }
+        let mut bindings = HashMap::new();
+        self.check_args(name, &function.params, args, arg_types, &mut bindings);
+
+        match name {
+            // a map on its own gives its keys, like it does in a `for` loop
+            "to_array" => match arg_types.first().cloned().flatten()?.non_optional() {
+                Type::Map(key, _) => Some(Type::Array(key.clone())),
+                ty => iterated_types(ty).map(|(_, item)| Type::Array(Box::new(item))),
+            },
+            // `panic` never returns, so what it gives back doesn't matter
+            "panic" => None,
+            _ => {
+                let ret = function.ret.instantiate(&bindings);
+                (!ret.contains_param()).then_some(ret)
+            }
+        }
+    }
+
+    /// Matches the arguments of a call to the parameters of function `name`
+    /// the way the call will at runtime, checking each argument's type
+    fn check_args(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        args: &[Argument],
+        arg_types: &[Option<Type>],
+        bindings: &mut HashMap<String, Type>,
+    ) {
+        // the number of items spread into the call isn't known
+        let spread = args.iter().any(|arg| arg.spread);
+        let variadic = params.last().is_some_and(|param| param.variadic);
+        let fixed = params.len() - variadic as usize;
+        let positional = args.iter().filter(|arg| arg.name.is_none()).count();
+
+        if positional > fixed && !variadic && !spread {
+            let message = format!(
+                "Function '{}' takes {} argument(s) but {} were given",
+                name,
+                params.len(),
+                positional
+            );
+            self.throw_error(message);
+            return;
+        }
+
+        let mut given = vec![false; params.len()];
+        let mut named = vec![false; params.len()];
+        let mut position = 0;
+        for (arg, arg_ty) in args.iter().zip(arg_types) {
+            let index = match &arg.name {
+                None if arg.spread => {
+                    // everything after a spread could end up anywhere
+                    position = params.len();
+                    continue;
+                }
+                None if position >= fixed => match variadic {
+                    true => fixed,
+                    false => continue,
+                },
+                None => {
+                    position += 1;
+                    position - 1
+                }
+                Some(arg_name) => match params.iter().position(|p| &p.name == arg_name) {
+                    Some(index) => index,
+                    None => {
+                        let message =
+                            format!("Function '{}' has no parameter named '{}'", name, arg_name);
+                        self.throw_error(message);
+                        continue;
+                    }
+                },
+            };
+
+            let param = &params[index];
+            // positional arguments pile up in a variadic parameter, unless it
+            // was already given an array by name
+            let duplicate = given[index] && (arg.name.is_some() || !param.variadic || named[index]);
+            if duplicate {
+                let message = format!(
+                    "Parameter '{}' of function '{}' was given more than one argument",
+                    param.name, name
+                );
+                self.throw_error(message);
+            }
+            given[index] = true;
+            named[index] |= arg.name.is_some();
+
+            // a named argument for a variadic parameter is the whole array
+            let ty = match arg.name {
+                Some(_) => param.binding_type(),
+                None => param.ty.clone(),
+            };
+            // an argument without a type may still be a literal that mixes
+            // types, whose items are checked by `expect_type`
+            let checked = arg_ty
+                .as_ref()
+                .is_some_and(|arg_ty| accepts(&ty, arg_ty, bindings));
+            if !checked {
+                // type parameters the call didn't bind could still be anything
+                let mut bound = rigid(&ty);
+                for (name, ty) in bound.iter_mut() {
+                    *ty = bindings.get(name).cloned().unwrap_or(Type::Any);
+                }
+                let ty = ty.instantiate(&bound);
+                let target = Target::Parameter {
+                    name: &param.name,
+                    function: name,
+                };
+                self.expect_type(&arg.value, arg_ty, &ty, target);
+            }
+        }
+
+        if spread {
+            return;
+        }
+        if let Some(param) = params
+            .iter()
+            .zip(&given)
+            .find(|(param, given)| !**given && param.default.is_none() && !param.variadic)
+            .map(|(param, _)| param)
+        {
+            let message = format!(
+                "Missing argument for parameter '{}' in call to function '{}'",
+                param.name, name
+            );
+            self.throw_error(message);
+        }
+    }
+}
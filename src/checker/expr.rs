@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+
+use super::types::{
+    accepts, castable, is_integer, is_numeric, null_type, rigid, type_name, unify, widest_number,
+};
+use super::Checker;
+use crate::error::{ErrorType::TypeError, Lint, Severity, VeloError, WARNING_INDICATOR};
+use crate::syntax::ast::{Ast, Expression, Param};
+use crate::syntax::lexer::{TokenType, Type};
+
+impl Checker {
+    /// Checks an expression and everything inside it, returning its type when
+    /// it's known
+    pub fn check_expr(&mut self, expr: &Expression) -> Option<Type> {
+        match expr {
+            Expression::Identifier(name) => match (self.lookup(name), name.split_once("::")) {
+                (Some(var), _) => var.current.clone(),
+                (None, Some((module, member))) if self.files.contains_key(module) => {
+                    self.export(module, member, "value")?.current
+                }
+                (None, _) => self.lib_function_value(name),
+            },
+            Expression::Null => Some(null_type()),
+            Expression::Short(_) => Some(Type::Short),
+            Expression::Int(_) => Some(Type::Int),
+            Expression::Large(_) => Some(Type::Large),
+            Expression::Float(_) => Some(Type::Float),
+            Expression::Bool(_) => Some(Type::Bool),
+            Expression::StringLiteral(_) => Some(Type::String),
+            Expression::Array(items) => {
+                let mut item_ty = None;
+                for (index, item) in items.iter().enumerate() {
+                    let ty = self.check_expr(item);
+                    item_ty = match (index, item_ty, ty) {
+                        (0, _, ty) => ty,
+                        (_, Some(a), Some(b)) => unify(&a, &b),
+                        _ => None,
+                    };
+                }
+                // arrays may mix types, they just don't get one
+                Some(Type::Array(Box::new(item_ty?)))
+            }
+            Expression::Map(entries) => {
+                let mut entry_ty = None;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    let key_ty = self.check_expr(key);
+                    self.expect_present(key, &key_ty, "used as a map key");
+                    if let Some(ty) = &key_ty {
+                        if !matches!(ty, Type::String | Type::Bool) && !is_numeric(ty) {
+                            let message = format!(
+                                "Map keys must be strings, numbers or bools, found a value of type '{}'",
+                                type_name(ty)
+                            );
+                            self.throw_error(message);
+                        }
+                    }
+
+                    let value_ty = self.check_expr(value);
+                    entry_ty = match (index, entry_ty, key_ty, value_ty) {
+                        (0, _, Some(key), Some(value)) => Some((key, value)),
+                        (_, Some((a_key, a_value)), Some(b_key), Some(b_value)) => {
+                            unify(&a_key, &b_key).zip(unify(&a_value, &b_value))
+                        }
+                        _ => None,
+                    };
+                }
+                let (key, value) = entry_ty?;
+                Some(Type::Map(Box::new(key), Box::new(value)))
+            }
+            Expression::CallExpr { name, params, line } => {
+                self.at_line(*line, |checker| checker.check_call(name, params))
+            }
+            Expression::Call {
+                callee,
+                params,
+                line,
+            } => self.at_line(*line, |checker| {
+                let ty = checker.check_expr(callee);
+                let arg_types = checker.check_arg_values(params);
+                checker.check_value_call("<function>", "This value", ty?, params, &arg_types)
+            }),
+            Expression::Lambda {
+                params,
+                body,
+                ret_type,
+            } => {
+                self.check_function("<lambda>", params, body, ret_type);
+                Some(Type::Function {
+                    params: params.iter().map(Param::binding_type).collect(),
+                    ret: Box::new(ret_type.clone()),
+                })
+            }
+            Expression::BinaryOp { lhs, op, rhs, line } => {
+                let (lhs, rhs) = match (lhs.as_ref(), rhs.as_ref()) {
+                    (Ast::Expression(lhs), Ast::Expression(rhs)) => (lhs, rhs),
+                    _ => return None,
+                };
+                self.at_line(*line, |checker| checker.check_binary(lhs, *op, rhs))
+            }
+            Expression::UnaryOp { op, expr } => {
+                let ty = self.check_expr(expr);
+                let action = format!("used with '{}'", op.symbol());
+                self.expect_present(expr, &ty, &action);
+
+                let ty = ty?.non_optional().clone();
+                let valid = match op {
+                    TokenType::Sub => is_numeric(&ty),
+                    TokenType::Not => ty == Type::Bool,
+                    TokenType::Tilde => is_integer(&ty),
+                    _ => true,
+                };
+                if !valid {
+                    let message = format!(
+                        "Cannot apply '{}' to a value of type '{}'",
+                        op.symbol(),
+                        type_name(&ty)
+                    );
+                    self.throw_error(message);
+                    return None;
+                }
+                Some(ty)
+            }
+            Expression::Range {
+                start, end, step, ..
+            } => {
+                for bound in [Some(start), Some(end), step.as_ref()]
+                    .into_iter()
+                    .flatten()
+                {
+                    let ty = self.check_expr(bound);
+                    self.expect_present(bound, &ty, "used as a range bound");
+                    if let Some(ty) = ty.filter(|ty| !is_numeric(ty.non_optional())) {
+                        let message = format!(
+                            "Ranges can only be made of numbers, found a value of type '{}'",
+                            type_name(&ty)
+                        );
+                        self.throw_error(message);
+                    }
+                }
+                Some(Type::Range)
+            }
+            Expression::Index {
+                target,
+                index,
+                safe,
+                line,
+            } => self.at_line(*line, |checker| {
+                let target_ty = checker.check_expr(target);
+                let item = checker.check_index(target, target_ty, index, *safe);
+                match safe {
+                    true => Some(Type::Optional(Box::new(item.unwrap_or(Type::Void)))),
+                    false => item,
+                }
+            }),
+            Expression::Cast { value, ty, line } => self.at_line(*line, |checker| {
+                let value_ty = checker.check_expr(value);
+                checker.expect_present(value, &value_ty, "cast");
+                if let Some(from) = value_ty.filter(|from| !castable(from.non_optional(), ty)) {
+                    let message = format!(
+                        "Cannot cast a value of type '{}' to '{}'",
+                        type_name(&from),
+                        ty
+                    );
+                    checker.throw_error(message);
+                }
+                Some(ty.clone())
+            }),
+            Expression::Block(body) => {
+                self.scopes.push(HashMap::new());
+                let ty = self.check_body(body);
+                self.scopes.pop();
+                ty
+            }
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+                line,
+            } => {
+                self.at_line(*line, |checker| {
+                    let cond_ty = checker.check_expr(cond);
+                    checker.expect_present(cond, &cond_ty, "used as a condition");
+                    checker.expect_bool(&cond_ty, "Condition of 'if'");
+                });
+                let (when_true, when_false) = self.narrowing(cond);
+
+                self.scopes.push(HashMap::new());
+                self.narrow(when_true);
+                let then_ty = self.check_body(then_branch);
+                self.scopes.pop();
+
+                self.scopes.push(HashMap::new());
+                self.narrow(when_false);
+                let else_ty = match else_branch {
+                    Some(else_branch) => self.check_expr(else_branch),
+                    None => Some(null_type()),
+                };
+                self.scopes.pop();
+
+                unify(&then_ty?, &else_ty?)
+            }
+            Expression::Try {
+                body,
+                error_name,
+                handler,
+            } => {
+                self.scopes.push(HashMap::new());
+                let body_ty = self.check_body(body);
+                self.scopes.pop();
+
+                self.scopes.push(HashMap::new());
+                if let Some(error_name) = error_name {
+                    self.declare(error_name, Some(Type::String), None);
+                }
+                let handler_ty = self.check_body(handler);
+                self.scopes.pop();
+
+                unify(&body_ty?, &handler_ty?)
+            }
+        }
+    }
+
+    /// Warns about comparing a value to `null` when its type says it can never
+    /// be null, for `velo lint`
+    fn null_comparison(
+        &mut self,
+        lhs: &Expression,
+        lhs_ty: &Option<Type>,
+        op: TokenType,
+        rhs: &Expression,
+        rhs_ty: &Option<Type>,
+    ) {
+        let ty = match (lhs, rhs) {
+            (Expression::Null, Expression::Null) => return,
+            (Expression::Null, _) => rhs_ty,
+            (_, Expression::Null) => lhs_ty,
+            _ => return,
+        };
+        // a type parameter could stand for an optional type
+        let never_null = ty
+            .as_ref()
+            .is_some_and(|ty| !ty.is_optional() && !matches!(ty, Type::Any | Type::Param(_)));
+        if !never_null || self.allowed.contains(&Lint::NullComparison) {
+            return;
+        }
+
+        let message = format!(
+            "{} \x1b[1mThis value can never be null, so comparing it to null is always {}\x1b[0m",
+            WARNING_INDICATOR,
+            op == TokenType::Ne
+        );
+        let warning = VeloError::warning(self.line, &message, TypeError, Lint::NullComparison)
+            .with_note(Severity::Help, "remove the comparison".to_string());
+        self.lints.push(warning);
+    }
+
+    fn check_binary(&mut self, lhs: &Expression, op: TokenType, rhs: &Expression) -> Option<Type> {
+        let lhs_ty = self.check_expr(lhs);
+
+        // the right hand side of `&&` and `||` only runs once the left hand
+        // side has been checked, so a null check there carries over
+        let narrowed = match op {
+            TokenType::And => Some(self.narrowing(lhs).0),
+            TokenType::Or => Some(self.narrowing(lhs).1),
+            _ => None,
+        };
+        if let Some(narrowed) = &narrowed {
+            self.scopes.push(HashMap::new());
+            self.narrow(narrowed.clone());
+        }
+        let rhs_ty = self.check_expr(rhs);
+        if narrowed.is_some() {
+            self.scopes.pop();
+        }
+
+        let action = format!("used with '{}'", op.symbol());
+        match op {
+            TokenType::QuestionQuestion => {
+                return match (lhs_ty, rhs_ty) {
+                    (_, Some(rhs)) if rhs.is_optional() => Some(rhs),
+                    (Some(lhs), rhs) if lhs == null_type() => rhs,
+                    (Some(lhs), Some(rhs)) => unify(lhs.non_optional(), &rhs),
+                    _ => None,
+                }
+            }
+            TokenType::EqEq | TokenType::Ne => {
+                self.null_comparison(lhs, &lhs_ty, op, rhs, &rhs_ty);
+                return Some(Type::Bool);
+            }
+            TokenType::In => {
+                self.expect_present(rhs, &rhs_ty, &action);
+                if let Some(ty) = rhs_ty.as_ref().map(Type::non_optional) {
+                    if !matches!(
+                        ty,
+                        Type::Array(_) | Type::Map(..) | Type::String | Type::Range
+                    ) {
+                        let message = format!(
+                            "Cannot check whether a value is in a value of type '{}'",
+                            type_name(ty)
+                        );
+                        self.throw_error(message);
+                    }
+                }
+                return Some(Type::Bool);
+            }
+            _ => {
+                self.expect_present(lhs, &lhs_ty, &action);
+                self.expect_present(rhs, &rhs_ty, &action);
+            }
+        }
+
+        let result = match op {
+            TokenType::And | TokenType::Or => Some(Type::Bool),
+            TokenType::Lt | TokenType::Gt | TokenType::LtEq | TokenType::GtEq => Some(Type::Bool),
+            _ => None,
+        };
+        let (lhs_ty, rhs_ty) = match (lhs_ty, rhs_ty) {
+            (Some(lhs), Some(rhs)) => (lhs.non_optional().clone(), rhs.non_optional().clone()),
+            _ => return result,
+        };
+
+        let numbers = is_numeric(&lhs_ty) && is_numeric(&rhs_ty);
+        let integers = is_integer(&lhs_ty) && is_integer(&rhs_ty);
+        let strings = lhs_ty == Type::String && rhs_ty == Type::String;
+        let ty = match op {
+            TokenType::Add if strings => Some(Type::String),
+            // dividing integers rounds towards zero, so every arithmetic
+            // operator keeps the widest type of its operands
+            TokenType::Add | TokenType::Sub | TokenType::Mul | TokenType::Div | TokenType::Mod
+                if numbers =>
+            {
+                Some(widest_number(&lhs_ty, &rhs_ty))
+            }
+            TokenType::Lt | TokenType::Gt | TokenType::LtEq | TokenType::GtEq
+                if numbers || strings =>
+            {
+                Some(Type::Bool)
+            }
+            TokenType::And | TokenType::Or if lhs_ty == Type::Bool && rhs_ty == Type::Bool => {
+                Some(Type::Bool)
+            }
+            TokenType::BitwiseOr
+            | TokenType::BitwiseXor
+            | TokenType::BitwiseAnd
+            | TokenType::ShiftLeft
+            | TokenType::ShiftRight
+                if integers =>
+            {
+                Some(widest_number(&lhs_ty, &rhs_ty))
+            }
+            _ => None,
+        };
+
+        if ty.is_none() {
+            let message = format!(
+                "Cannot apply '{}' to values of type '{}' and '{}'",
+                op.symbol(),
+                type_name(&lhs_ty),
+                type_name(&rhs_ty)
+            );
+            self.throw_error(message);
+        }
+        ty.or(result)
+    }
+
+    /// Checks `target[index]`, where the target is of type `target_ty`,
+    /// returning the type of the item
+    pub fn check_index(
+        &mut self,
+        target: &Expression,
+        target_ty: Option<Type>,
+        index: &Expression,
+        safe: bool,
+    ) -> Option<Type> {
+        if !safe {
+            self.expect_present(target, &target_ty, "indexed");
+        }
+        let index_ty = self.check_expr(index);
+        self.expect_present(index, &index_ty, "used as an index");
+
+        let target_ty = target_ty?.non_optional().clone();
+        let index_ty = index_ty?.non_optional().clone();
+        match (&target_ty, &index_ty) {
+            (Type::Map(key, value), index_ty) => {
+                if !accepts(key, index_ty, &mut rigid(key)) {
+                    let message = format!(
+                        "Map has keys of type '{}' but was indexed with a value of type '{}'",
+                        key,
+                        type_name(index_ty)
+                    );
+                    self.throw_error(message);
+                }
+                Some(*value.clone())
+            }
+            (Type::Array(item), index_ty) if is_integer(index_ty) => Some(*item.clone()),
+            (Type::Array(_), Type::Range) => Some(target_ty.clone()),
+            (Type::String, index_ty) if is_integer(index_ty) || *index_ty == Type::Range => {
+                Some(Type::String)
+            }
+            (Type::Array(_) | Type::String, index_ty) => {
+                let message = format!(
+                    "Cannot index with a value of type '{}'",
+                    type_name(index_ty)
+                );
+                self.throw_error(message);
+                None
+            }
+            (Type::Param(_), _) => None,
+            (target_ty, _) => {
+                let message = format!(
+                    "Cannot index into a value of type '{}'",
+                    type_name(target_ty)
+                );
+                self.throw_error(message);
+                None
+            }
+        }
+    }
+
+    /// Reports a value of known type that isn't a bool where one is needed
+    pub fn expect_bool(&mut self, ty: &Option<Type>, what: &str) {
+        if let Some(ty) = ty {
+            if *ty.non_optional() != Type::Bool {
+                let message = format!(
+                    "{} must be a bool, found a value of type '{}'",
+                    what,
+                    type_name(ty)
+                );
+                self.throw_error(message);
+            }
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use crate::error::{
+    ErrorType::TypeError, Lint, Severity, VeloError, ERROR_INDICATOR, WARNING_INDICATOR,
+};
+use crate::syntax::ast::{Ast, Expression, FunctionBody, Statement};
+use crate::syntax::lexer::Type;
+
+/// How a statement leaves the block it's in for good, so that the code after
+/// it can never run. Each holds the line of the statement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Return(usize),
+    Break(usize),
+    Continue(usize),
+    Panic(usize),
+    /// A `loop` nothing breaks out of
+    Loop(usize),
+    /// An `if` every branch of which exits
+    Branches(usize),
+}
+
+impl Exit {
+    fn line(&self) -> usize {
+        match self {
+            Exit::Return(line)
+            | Exit::Break(line)
+            | Exit::Continue(line)
+            | Exit::Panic(line)
+            | Exit::Loop(line)
+            | Exit::Branches(line) => *line,
+        }
+    }
+
+    /// How the statement is referred to in warnings
+    fn describe(&self) -> &'static str {
+        match self {
+            Exit::Return(_) => "'return'",
+            Exit::Break(_) => "'break'",
+            Exit::Continue(_) => "'continue'",
+            Exit::Panic(_) => "call to 'panic'",
+            Exit::Loop(_) => "'loop'",
+            Exit::Branches(_) => "'if'",
+        }
+    }
+}
+
+/// Follows the control flow of every block in a program, warning about code
+/// that can never run and reporting functions that should return a value but
+/// can reach the end of their body without one
+pub fn flow(nodes: &[Ast]) -> (Vec<VeloError>, Vec<VeloError>) {
+    let mut flow = Flow {
+        line: 0,
+        allowed: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut exits = Vec::new();
+    for node in nodes {
+        let exit = match node {
+            Ast::Statement(stmt) => {
+                flow.statement(stmt);
+                (statement_exit(stmt), stmt.allows(Lint::UnreachableCode))
+            }
+            Ast::Expression(expr) => {
+                flow.expr(expr);
+                (expr_exit(expr), false)
+            }
+        };
+        exits.push(exit);
+    }
+    flow.unreachable(&exits);
+
+    (flow.errors, flow.warnings)
+}
+
+/// How running `body` always leaves it early, if it does
+pub fn body_exit(body: &FunctionBody) -> Option<Exit> {
+    body.stmts
+        .iter()
+        .find_map(statement_exit)
+        .or_else(|| body.value.as_deref().and_then(expr_exit))
+}
+
+fn statement_exit(stmt: &Statement) -> Option<Exit> {
+    match stmt.unattributed() {
+        Statement::Return { line, .. } => Some(Exit::Return(*line)),
+        Statement::Break { line } => Some(Exit::Break(*line)),
+        Statement::Continue { line } => Some(Exit::Continue(*line)),
+        Statement::Loop { body, line } if !breaks(body) => Some(Exit::Loop(*line)),
+        // a value that always leaves the block never gets stored
+        Statement::ExprStmt(expr)
+        | Statement::VariableAssignment { value: expr, .. }
+        | Statement::Reassignment { value: expr, .. }
+        | Statement::IndexAssignment { value: expr, .. } => expr_exit(expr),
+        _ => None,
+    }
+}
+
+fn expr_exit(expr: &Expression) -> Option<Exit> {
+    match expr {
+        Expression::Block(body) => body_exit(body),
+        Expression::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            line,
+            ..
+        } => {
+            body_exit(then_branch)?;
+            expr_exit(else_branch)?;
+            Some(Exit::Branches(*line))
+        }
+        Expression::CallExpr { name, line, .. } if name == "panic" => Some(Exit::Panic(*line)),
+        _ => None,
+    }
+}
+
+/// Whether a `break` in `body` leaves the loop it belongs to. Breaks inside
+/// nested loops only leave those
+fn breaks(body: &FunctionBody) -> bool {
+    body.stmts.iter().any(|stmt| match stmt.unattributed() {
+        Statement::Break { .. } => true,
+        Statement::ExprStmt(expr)
+        | Statement::VariableAssignment { value: expr, .. }
+        | Statement::Reassignment { value: expr, .. }
+        | Statement::IndexAssignment { value: expr, .. } => expr_breaks(expr),
+        _ => false,
+    }) || body.value.as_deref().is_some_and(expr_breaks)
+}
+
+fn expr_breaks(expr: &Expression) -> bool {
+    match expr {
+        Expression::Block(body) => breaks(body),
+        Expression::If {
+            then_branch,
+            else_branch,
+            ..
+        } => breaks(then_branch) || else_branch.as_deref().is_some_and(expr_breaks),
+        Expression::Try { body, handler, .. } => breaks(body) || breaks(handler),
+        _ => false,
+    }
+}
+
+/// Whether running `body` ends in a value or leaves it early, rather than
+/// just running off its end
+fn finishes(body: &FunctionBody) -> bool {
+    body_exit(body).is_some() || body.value.as_deref().is_some_and(gives_value)
+}
+
+fn gives_value(expr: &Expression) -> bool {
+    match expr {
+        Expression::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => finishes(then_branch) && gives_value(else_branch),
+        // an `if` without an `else` gives `null` when its condition is false,
+        // which the type checker reports when the function can't return it
+        Expression::If { then_branch, .. } => then_branch.value.is_some(),
+        Expression::Block(body) => finishes(body),
+        Expression::Try { body, handler, .. } => finishes(body) && finishes(handler),
+        _ => true,
+    }
+}
+
+struct Flow {
+    /// The line of the statement or expression being followed
+    line: usize,
+    /// The lints turned off by the attributes of the statements around the
+    /// code being followed
+    allowed: Vec<Lint>,
+    errors: Vec<VeloError>,
+    warnings: Vec<VeloError>,
+}
+
+impl Flow {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableAssignment { value, line, .. }
+            | Statement::Reassignment { value, line, .. }
+            | Statement::Return { value, line } => {
+                self.line = *line;
+                self.expr(value);
+            }
+            Statement::IndexAssignment {
+                indices,
+                value,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                for index in indices {
+                    self.expr(index);
+                }
+                self.expr(value);
+            }
+            Statement::Function {
+                name,
+                body,
+                ret_type,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                self.function(&format!("Function '{}'", name), body, ret_type);
+            }
+            Statement::For {
+                iterable,
+                body,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                self.expr(iterable);
+                self.body(body);
+            }
+            Statement::Loop { body, .. } => self.body(body),
+            Statement::Export(stmt) => self.statement(stmt),
+            Statement::Attributed { attribute, stmt } => {
+                let outer = self.allowed.len();
+                self.allowed.extend(attribute.allowed());
+                self.statement(stmt);
+                self.allowed.truncate(outer);
+            }
+            Statement::ExprStmt(expr) => self.expr(expr),
+            Statement::ImportPath { .. } | Statement::Break { .. } | Statement::Continue { .. } => {
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Lambda { body, ret_type, .. } => {
+                self.function("This function", body, ret_type)
+            }
+            Expression::Block(body) => self.body(body),
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+                line,
+            } => {
+                self.line = *line;
+                self.expr(cond);
+                self.body(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.expr(else_branch);
+                }
+            }
+            Expression::Try { body, handler, .. } => {
+                self.body(body);
+                self.body(handler);
+            }
+            Expression::CallExpr { params, line, .. } => {
+                self.line = *line;
+                for arg in params {
+                    self.expr(&arg.value);
+                }
+            }
+            Expression::Call {
+                callee,
+                params,
+                line,
+            } => {
+                self.line = *line;
+                self.expr(callee);
+                for arg in params {
+                    self.expr(&arg.value);
+                }
+            }
+            Expression::BinaryOp { lhs, rhs, line, .. } => {
+                self.line = *line;
+                for side in [lhs, rhs] {
+                    if let Ast::Expression(side) = side.as_ref() {
+                        self.expr(side);
+                    }
+                }
+            }
+            Expression::UnaryOp { expr, .. } => self.expr(expr),
+            Expression::Cast { value, .. } => self.expr(value),
+            Expression::Index { target, index, .. } => {
+                self.expr(target);
+                self.expr(index);
+            }
+            Expression::Range {
+                start, end, step, ..
+            } => {
+                self.expr(start);
+                self.expr(end);
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+            }
+            Expression::Array(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+            Expression::Map(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Follows the body of a function, which `describe`s it in errors
+    fn function(&mut self, describe: &str, body: &FunctionBody, ret_type: &Type) {
+        let line = self.line;
+        self.body(body);
+
+        if *ret_type != Type::Void && !finishes(body) {
+            let message = format!(
+                "{} \x1b[1m{} should return '{}' but can reach the end of its body without returning a value\x1b[0m",
+                ERROR_INDICATOR, describe, ret_type
+            );
+            let error = VeloError::error(line, &message, TypeError).with_note(
+                Severity::Help,
+                "end its body with the value to return, or 'return' one".to_string(),
+            );
+            self.errors.push(error);
+        }
+    }
+
+    fn body(&mut self, body: &FunctionBody) {
+        let mut exits = Vec::new();
+        for stmt in &body.stmts {
+            self.statement(stmt);
+            exits.push((statement_exit(stmt), stmt.allows(Lint::UnreachableCode)));
+        }
+        if let Some(value) = &body.value {
+            self.expr(value);
+            exits.push((expr_exit(value), false));
+        }
+        self.unreachable(&exits);
+    }
+
+    /// Warns once about everything after the first statement of a block that
+    /// exits it, given how each statement exits and whether an `@allow` before
+    /// it turns the warning off
+    fn unreachable(&mut self, exits: &[(Option<Exit>, bool)]) {
+        let (index, exit, allowed) = match exits
+            .iter()
+            .enumerate()
+            .find_map(|(index, (exit, allowed))| Some((index, (*exit)?, *allowed)))
+        {
+            Some(found) => found,
+            None => return,
+        };
+        if index + 1 == exits.len() || allowed || self.allowed.contains(&Lint::UnreachableCode) {
+            return;
+        }
+
+        let message = format!(
+            "{} \x1b[1mCode after this {} can never run\x1b[0m",
+            WARNING_INDICATOR,
+            exit.describe()
+        );
+        let mut warning =
+            VeloError::warning(exit.line(), &message, TypeError, Lint::UnreachableCode);
+        warning = match exit {
+            Exit::Loop(_) => warning
+                .with_note(
+                    Severity::Note,
+                    "nothing breaks out of the loop, so it never ends".to_string(),
+                )
+                .with_note(
+                    Severity::Help,
+                    "remove the code after it, or add a 'break' to the loop".to_string(),
+                ),
+            Exit::Branches(_) => warning
+                .with_note(
+                    Severity::Note,
+                    "every branch of it returns, breaks, continues or panics".to_string(),
+                )
+                .with_note(Severity::Help, "remove the code after it".to_string()),
+            exit => warning.with_note(
+                Severity::Help,
+                format!("remove it, or move it before the {}", exit.describe()),
+            ),
+        };
+        self.warnings.push(warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+    use crate::syntax::parse::Parser;
+
+    /// The lines and messages of errors or warnings
+    type Found = Vec<(usize, String)>;
+
+    /// The errors and the warnings found following `source`
+    fn followed(source: &str) -> (Found, Found) {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        let nodes = Parser::new(&tokens)
+            .parse()
+            .unwrap_or_else(|errors| panic!("failed to parse {:?}: {:?}", source, errors));
+        let (errors, warnings) = flow(&nodes);
+        let found = |errors: Vec<VeloError>| {
+            errors
+                .into_iter()
+                .map(|error| (error.line, error.message))
+                .collect()
+        };
+        (found(errors), found(warnings))
+    }
+
+    /// The line and the exit of the one unreachable code warning in `source`
+    fn unreachable_after(source: &str) -> (usize, String) {
+        let (_, warnings) = followed(source);
+        match warnings.as_slice() {
+            [(line, message)] => {
+                let exit = message
+                    .split("Code after this ")
+                    .nth(1)
+                    .and_then(|rest| rest.split(" can never run").next())
+                    .unwrap_or_else(|| panic!("unexpected warning {:?}", message));
+                (*line, exit.to_string())
+            }
+            warnings => panic!("expected one warning in {:?}, found {:?}", source, warnings),
+        }
+    }
+
+    fn missing_return(source: &str) -> bool {
+        let (errors, _) = followed(source);
+        errors
+            .iter()
+            .any(|(_, message)| message.contains("can reach the end of its body"))
+    }
+
+    #[test]
+    fn code_after_an_exit_is_unreachable() {
+        let cases = [
+            ("fun f() {\n  return;\n  println(1);\n}", 2, "'return'"),
+            ("for i in 1..3 {\n  break;\n  println(i);\n}", 2, "'break'"),
+            (
+                "for i in 1..3 {\n  continue;\n  println(i);\n}",
+                2,
+                "'continue'",
+            ),
+            ("panic(\"no\");\nprintln(1);", 1, "call to 'panic'"),
+            ("loop { }\nprintln(1);", 1, "'loop'"),
+            (
+                "fun f(b bool) {\n  if b { return; } else { panic(\"no\"); }\n  println(1);\n}",
+                2,
+                "'if'",
+            ),
+        ];
+        for (source, line, exit) in cases {
+            assert_eq!(
+                unreachable_after(source),
+                (line, exit.to_string()),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn each_block_is_warned_about_once() {
+        let (_, warnings) =
+            followed("fun f() {\n  return;\n  println(1);\n  return;\n  println(2);\n}");
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    }
+
+    #[test]
+    fn exits_that_may_not_happen_are_not_warned_about() {
+        let sources = [
+            "fun f(b bool) { if b { return; } println(1); }",
+            "loop { if true { break; } }\nprintln(1);",
+            "loop { for i in 1..3 { } x := if true { break; } else { 1 }; println(x); }\nprintln(1);",
+            "xs := [0];\nloop { xs[0] = if true { break; } else { 1 }; }\nprintln(xs);",
+            "fun f() { return; }",
+        ];
+        for source in sources {
+            let (_, warnings) = followed(source);
+            assert!(warnings.is_empty(), "{}: {:?}", source, warnings);
+        }
+        // a `break` only leaves the loop it's in
+        assert_eq!(
+            unreachable_after("loop { for i in 1..3 { break; } }\nprintln(1);"),
+            (1, "'loop'".to_string())
+        );
+    }
+
+    #[test]
+    fn functions_returning_a_value_have_to_finish() {
+        assert!(missing_return(
+            "fun f(n int) -> int { if n < 0 { return -1; } }"
+        ));
+        assert!(missing_return(
+            "fun f(b bool) -> int { if b { return 1; } else { } }"
+        ));
+        assert!(missing_return("f := fun() -> int { };"));
+        assert!(!missing_return(
+            "fun f(n int) -> int { if n < 0 { return -1; } n }"
+        ));
+        assert!(!missing_return("fun f() -> int { loop { } }"));
+        assert!(!missing_return("fun f() -> int { panic(\"no\"); }"));
+        assert!(!missing_return("fun f() -> int { try { 1 } catch { 2 } }"));
+        assert!(!missing_return(
+            "fun f() -> int { x := if true { return 1; } else { return 2; }; }"
+        ));
+        assert!(!missing_return("fun f() { }"));
+    }
+}
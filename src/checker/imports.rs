@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{check_with, Exports};
+use crate::error::{ErrorType::NameError, VeloError, ERROR_INDICATOR};
+use crate::syntax::ast::{Ast, Statement};
+use crate::syntax::lexer::Lexer;
+use crate::syntax::parse::Parser;
+
+/// Follows the files a program imports without running anything, checking
+/// each of them once. Errors in an imported file are reported in that file,
+/// and a file that can't be found or checked at the `import` of it
+#[derive(Debug, Default)]
+pub struct Imports {
+    /// The exports of the files checked so far, or the error for importing
+    /// each of them when they aren't free of errors
+    checked: HashMap<PathBuf, Result<Exports, String>>,
+    /// The files being checked, for reporting circular imports
+    stack: Vec<PathBuf>,
+}
+
+impl Imports {
+    pub fn new(entry: Option<&Path>) -> Self {
+        let mut imports = Self::default();
+        if let Some(path) = entry.and_then(|p| fs::canonicalize(p).ok()) {
+            imports.stack.push(path);
+        }
+        imports
+    }
+
+    /// Checks the files imported by `nodes`, the program in `file`, returning
+    /// the errors found in them and the exports of each, by the name of its
+    /// module
+    pub fn check(
+        &mut self,
+        nodes: &[Ast],
+        file: Option<&Path>,
+    ) -> (Vec<VeloError>, HashMap<String, Exports>) {
+        let mut errors = Vec::new();
+        let mut files = HashMap::new();
+        for node in nodes {
+            if let Ast::Statement(Statement::ImportPath {
+                path,
+                is_library: false,
+                line,
+            }) = node
+            {
+                match self.check_file(path, file, &mut errors) {
+                    Ok(exports) => {
+                        let name = Path::new(path)
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                            .unwrap_or_else(|| path.clone());
+                        files.insert(name, exports);
+                    }
+                    Err(message) => {
+                        let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
+                        errors.push(VeloError::error(*line, &message, NameError));
+                    }
+                }
+            }
+        }
+        (errors, files)
+    }
+
+    /// Checks the file imported as `path` from `from`, adding the errors in it
+    /// to `errors`. Gives its exports, or the error for the `import` itself
+    fn check_file(
+        &mut self,
+        path: &str,
+        from: Option<&Path>,
+        errors: &mut Vec<VeloError>,
+    ) -> Result<Exports, String> {
+        // relative paths are resolved against the directory of the importing
+        // file, like they are at runtime
+        let joined = from
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""))
+            .join(path);
+        let resolved = fs::canonicalize(&joined)
+            .map_err(|_| format!("Could not find module '{}' ({})", path, joined.display()))?;
+
+        if let Some(start) = self.stack.iter().position(|p| *p == resolved) {
+            let chain: Vec<String> = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(format!("Circular import detected: {}", chain.join(" -> ")));
+        }
+
+        if let Some(result) = self.checked.get(&resolved) {
+            return result.clone();
+        }
+        let result = self.check_new_file(path, &joined, &resolved, errors);
+        self.checked.insert(resolved, result.clone());
+        result
+    }
+
+    /// Parses and checks a file that wasn't imported before, adding the errors
+    /// in it to `errors`
+    fn check_new_file(
+        &mut self,
+        path: &str,
+        joined: &Path,
+        resolved: &Path,
+        errors: &mut Vec<VeloError>,
+    ) -> Result<Exports, String> {
+        let contents = fs::read_to_string(resolved)
+            .map_err(|_| format!("Error reading module: {}", joined.display()))?;
+        let tokens = Lexer::new(&contents).tokenize().tokens;
+        let nodes = match Parser::new(&tokens).parse() {
+            Ok(nodes) => nodes,
+            Err(parse_errors) => {
+                errors.extend(parse_errors.into_iter().map(|e| e.in_file(joined)));
+                return Err(format!("Could not parse module '{}'", path));
+            }
+        };
+
+        self.stack.push(resolved.to_path_buf());
+        let checked = check_with(&nodes, Some(joined), self);
+        self.stack.pop();
+        match checked {
+            Ok(checked) => Ok(checked.exports),
+            Err(check_errors) => {
+                errors.extend(check_errors.into_iter().map(|e| e.in_file(joined)));
+                Err(format!("Could not check module '{}'", path))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::check;
+
+    /// Writes `files` to a new directory and checks the first of them,
+    /// giving the errors found along with the file each is in
+    fn check_files(name: &str, files: &[(&str, &str)]) -> Vec<(Option<String>, String)> {
+        let dir =
+            std::env::temp_dir().join(format!("velo-imports-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            fs::write(dir.join(file), source).unwrap();
+        }
+
+        let entry = dir.join(files[0].0);
+        let tokens = Lexer::new(files[0].1).tokenize().tokens;
+        let nodes = Parser::new(&tokens).parse().unwrap();
+        let errors = match check(&nodes, Some(&entry)) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|error| {
+                    let file = error.file.map(|file| {
+                        Path::new(&file)
+                            .file_name()
+                            .unwrap()
+                            .to_string_lossy()
+                            .to_string()
+                    });
+                    (file, error.message)
+                })
+                .collect(),
+        };
+        fs::remove_dir_all(&dir).ok();
+        errors
+    }
+
+    #[test]
+    fn circular_imports_are_reported_at_the_import() {
+        let errors = check_files(
+            "cycle",
+            &[
+                ("main.velo", "import \"a.velo\";"),
+                ("a.velo", "import \"b.velo\";"),
+                ("b.velo", "import \"a.velo\";"),
+            ],
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|(file, message)| file.as_deref() == Some("b.velo")
+                    && message.contains("Circular import detected")),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn a_file_importing_itself_is_circular() {
+        let errors = check_files("self", &[("main.velo", "import \"main.velo\";")]);
+        assert!(
+            matches!(errors.as_slice(), [(None, message)] if message.contains("Circular import detected")),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn a_file_imported_twice_is_not_circular() {
+        let errors = check_files(
+            "diamond",
+            &[
+                ("main.velo", "import \"a.velo\"; import \"b.velo\";"),
+                ("a.velo", "import \"c.velo\";"),
+                ("b.velo", "import \"c.velo\";"),
+                ("c.velo", "export x := 1;"),
+            ],
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn errors_in_an_imported_file_are_reported_in_it() {
+        let errors = check_files(
+            "nested",
+            &[
+                ("main.velo", "import \"bad.velo\";"),
+                ("bad.velo", "x: int := \"no\";"),
+            ],
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|(file, _)| file.as_deref() == Some("bad.velo")),
+            "{:?}",
+            errors
+        );
+        assert!(
+            errors.iter().any(|(file, message)| file.is_none()
+                && message.contains("Could not check module 'bad.velo'")),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn missing_modules_are_reported() {
+        let errors = check_files("missing", &[("main.velo", "import \"nowhere.velo\";")]);
+        assert!(
+            matches!(errors.as_slice(), [(None, message)] if message.contains("Could not find module 'nowhere.velo'")),
+            "{:?}",
+            errors
+        );
+    }
+}
@@ -0,0 +1,844 @@
+mod call;
+mod expr;
+mod flow;
+mod imports;
+mod narrow;
+mod resolve;
+mod stmt;
+mod types;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{ErrorType::TypeError, Lint, VeloError, ERROR_INDICATOR};
+use crate::runtime::environment::LibFunction;
+use crate::runtime::value::Value;
+use crate::syntax::ast::{Ast, Expression, Param, Statement};
+use crate::syntax::lexer::{TokenType, Type};
+use imports::Imports;
+use types::{accepts, is_integer, null_type, rigid, type_name, unify};
+
+/// A variable declared with `:=`, together with the type it was declared
+/// with or inferred from its value
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub line: usize,
+    /// `None` when the type is only known at runtime, e.g. for `input()`
+    pub ty: Option<Type>,
+}
+
+impl Binding {
+    /// How the type of the binding is shown to the user
+    pub fn type_name(&self) -> String {
+        match &self.ty {
+            Some(ty) => type_name(ty),
+            None => "unknown".to_string(),
+        }
+    }
+}
+
+/// What checking a program found out about it
+pub struct Checked {
+    /// The type of every `:=` binding in the program
+    pub bindings: Vec<Binding>,
+    /// Problems that don't stop the program from running, like unused
+    /// variables
+    pub warnings: Vec<VeloError>,
+    /// Style warnings that need the types of values, which only `velo lint`
+    /// shows
+    pub lints: Vec<VeloError>,
+    /// What the program exports, for checking the files that import it
+    exports: Exports,
+}
+
+/// The names a file exports, with what the checker knows about them
+type Exports = HashMap<String, Variable>;
+
+/// Checks the names and types of a parsed program before it's run, reporting
+/// every undefined name and mismatch found. The files it imports are checked
+/// too, relative to `file`, the path of the program
+pub fn check(nodes: &[Ast], file: Option<&Path>) -> Result<Checked, Vec<VeloError>> {
+    check_with(nodes, file, &mut Imports::new(file))
+}
+
+/// `check`, skipping the files `imports` already checked for the program
+/// importing this one
+fn check_with(
+    nodes: &[Ast],
+    file: Option<&Path>,
+    imports: &mut Imports,
+) -> Result<Checked, Vec<VeloError>> {
+    let (import_errors, files) = imports.check(nodes, file);
+    let (mut errors, mut warnings) = resolve::resolve(nodes);
+    let (mut flow_errors, mut flow_warnings) = flow::flow(nodes);
+    errors.append(&mut flow_errors);
+    warnings.append(&mut flow_warnings);
+    warnings.sort_by_key(|warning| warning.line);
+
+    let mut checker = Checker::new();
+    checker.files = files;
+    checker.declare_functions(nodes.iter().filter_map(|node| match node {
+        Ast::Statement(stmt) => Some(stmt),
+        Ast::Expression(_) => None,
+    }));
+
+    for node in nodes {
+        match node {
+            Ast::Expression(expr) => {
+                checker.check_expr(expr);
+            }
+            Ast::Statement(stmt) => checker.check_statement(stmt),
+        }
+    }
+
+    errors.append(&mut checker.errors);
+    errors.sort_by_key(|error| error.line);
+    // the errors of imported files come first, since they're in other files
+    let errors: Vec<VeloError> = import_errors.into_iter().chain(errors).collect();
+    // what's exported is known by the type it was declared with, whatever
+    // the rest of the file narrowed it to
+    let exports = nodes
+        .iter()
+        .filter_map(|node| match node {
+            Ast::Statement(stmt) => match stmt.unattributed() {
+                Statement::Export(stmt) => stmt.declared_name(),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter_map(|name| {
+            let variable = checker.lookup(name)?;
+            let export = Variable {
+                current: variable.declared.clone(),
+                ..variable.clone()
+            };
+            Some((name.to_string(), export))
+        })
+        .collect();
+    match errors.is_empty() {
+        true => Ok(Checked {
+            bindings: checker.bindings,
+            warnings,
+            lints: checker.lints,
+            exports,
+        }),
+        false => Err(errors),
+    }
+}
+
+/// What the checker knows about a variable
+#[derive(Debug, Clone)]
+struct Variable {
+    /// The type it was declared with, or inferred from its value
+    declared: Option<Type>,
+    /// The type at the current point of the program, which differs from the
+    /// declared one after a null check
+    current: Option<Type>,
+    origin: Origin,
+    /// The parameters of the function it holds, for checking calls
+    params: Option<Vec<Param>>,
+    /// Whether this entry only narrows a variable of an outer scope
+    narrowed: bool,
+}
+
+/// Where the type of a variable comes from, which decides whether new values
+/// have to be of that type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Origin {
+    /// Written out, like `x: int := 5` or a parameter
+    Annotated,
+    /// Taken from the value of a `:=` declaration
+    Inferred,
+    /// Given by the language, like a loop variable
+    Implicit,
+}
+
+/// Where a value ends up, for describing a mismatch
+enum Target<'a> {
+    Variable(&'a str),
+    /// A variable without a written out type, given a new value
+    Inferred(&'a str),
+    /// An item of the collection in a variable, i.e. `xs[0] = value`
+    Item(&'a str),
+    Parameter {
+        name: &'a str,
+        function: &'a str,
+    },
+    Default {
+        name: &'a str,
+        function: &'a str,
+    },
+    Return(&'a str),
+}
+
+impl Target<'_> {
+    fn describe(&self, ty: &Type) -> String {
+        match self {
+            Target::Variable(name) => format!("variable '{}' is declared as '{}'", name, ty),
+            Target::Inferred(name) => {
+                format!("variable '{}' was inferred to be '{}'", name, ty)
+            }
+            Target::Item(name) => format!("the items of '{}' are of type '{}'", name, ty),
+            Target::Parameter { name, function } | Target::Default { name, function } => {
+                format!(
+                    "parameter '{}' of function '{}' is of type '{}'",
+                    name, function, ty
+                )
+            }
+            Target::Return(function) => format!("function '{}' should return '{}'", function, ty),
+        }
+    }
+
+    /// What the value is called in a mismatch
+    fn value(&self) -> &'static str {
+        match self {
+            Target::Variable(_) => "its value",
+            Target::Inferred(_) => "its new value",
+            Target::Item(_) => "the value",
+            Target::Parameter { .. } => "the argument",
+            Target::Default { .. } => "its default value",
+            Target::Return(_) => "the returned value",
+        }
+    }
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Variable>>,
+    /// Names and return types of the functions currently being checked
+    functions: Vec<(String, Type)>,
+    /// The functions of the library modules that were imported, by the name
+    /// they're called with
+    modules: HashMap<String, Vec<LibFunction>>,
+    /// The exports of the files that were imported, by the name they're
+    /// called with
+    files: HashMap<String, Exports>,
+    /// The line of the statement or expression being checked
+    line: usize,
+    bindings: Vec<Binding>,
+    errors: Vec<VeloError>,
+    /// Style warnings that need the types of values, see `Checked::lints`
+    lints: Vec<VeloError>,
+    /// The lints turned off by the attributes of the statements around the
+    /// code being checked
+    allowed: Vec<Lint>,
+}
+
+impl Checker {
+    fn new() -> Checker {
+        Checker {
+            scopes: vec![HashMap::new()],
+            functions: Vec::new(),
+            modules: HashMap::new(),
+            files: HashMap::new(),
+            line: 0,
+            bindings: Vec::new(),
+            errors: Vec::new(),
+            lints: Vec::new(),
+            allowed: Vec::new(),
+        }
+    }
+
+    /// Reports `value`, of type `value_ty`, when it can't be given to `target`
+    /// of type `ty`
+    fn expect_type(
+        &mut self,
+        value: &Expression,
+        value_ty: &Option<Type>,
+        ty: &Type,
+        target: Target,
+    ) {
+        // a literal integer can be stored as any integer type it fits in
+        if let Some(num) = literal_integer(value) {
+            if is_integer(ty.non_optional()) {
+                if !Value::Large(num).matches_type(ty) {
+                    let message = format!(
+                        "Mismatched types: {} but {} {} doesn't fit in it",
+                        target.describe(ty),
+                        target.value(),
+                        num
+                    );
+                    self.throw_error(message);
+                }
+                return;
+            }
+        }
+        if let Some(value_ty) = value_ty {
+            if accepts(ty, value_ty, &mut rigid(ty)) {
+                return;
+            }
+        }
+
+        // the items of an array or map literal are checked one by one, so
+        // literals among them can be stored as the item type, and literals that
+        // mix types get checked at all
+        if literal_items(value, ty).is_some() {
+            if let Some((kind, item_ty)) = self.mismatched_item(value, ty) {
+                let message = format!(
+                    "Mismatched types: {} but {} holds {} of type '{}'",
+                    target.describe(ty),
+                    target.value(),
+                    kind,
+                    type_name(&item_ty)
+                );
+                self.throw_error(message);
+            }
+            return;
+        }
+        let value_ty = match value_ty {
+            Some(value_ty) => value_ty,
+            // an `if` whose branches give values of different types doesn't
+            // get one either
+            None => {
+                let mismatched = self
+                    .branch_types(value)
+                    .into_iter()
+                    .find(|branch_ty| !accepts(ty, branch_ty, &mut rigid(ty)));
+                if let Some(branch_ty) = mismatched {
+                    let message = format!(
+                        "Mismatched types: {} but a branch of the 'if' gives a value of type '{}'",
+                        target.describe(ty),
+                        type_name(&branch_ty)
+                    );
+                    self.throw_error(message);
+                }
+                return;
+            }
+        };
+
+        // a value that would fit if it weren't for `null` gets a hint on how
+        // to deal with that instead
+        let null_only = *value_ty != null_type()
+            && value_ty.is_optional()
+            && accepts(ty, value_ty.non_optional(), &mut rigid(ty));
+        let message = match null_only {
+            true => format!(
+                "{} may be null, but {}. Check it against null or give it a default with '??' first",
+                describe(value),
+                target.describe(ty)
+            ),
+            false => format!(
+                "Mismatched types: {} but {} is of type '{}'",
+                target.describe(ty),
+                target.value(),
+                type_name(value_ty)
+            ),
+        };
+        self.throw_error(message);
+    }
+
+    /// The first item of an array or map literal that can't be given to a
+    /// collection of type `ty`, as what kind of item it is and its type
+    fn mismatched_item(&mut self, value: &Expression, ty: &Type) -> Option<(&'static str, Type)> {
+        literal_items(value, ty)?
+            .into_iter()
+            .find_map(|(item, ty, kind)| {
+                let item_ty = self.quiet_type(item);
+                if let Some(num) = literal_integer(item) {
+                    if is_integer(ty.non_optional()) {
+                        let fits = Value::Large(num).matches_type(ty);
+                        return item_ty.filter(|_| !fits).map(|item_ty| (kind, item_ty));
+                    }
+                }
+                match item_ty {
+                    Some(item_ty) if accepts(ty, &item_ty, &mut rigid(ty)) => None,
+                    Some(item_ty) if literal_items(item, ty).is_none() => Some((kind, item_ty)),
+                    _ => self.mismatched_item(item, ty),
+                }
+            })
+    }
+
+    /// Reports an `if` whose branches give values of types that have nothing
+    /// in common, when nothing else says which type it should be
+    fn expect_same_branches(&mut self, value: &Expression) {
+        let types = self.branch_types(value);
+        let mut types = types.iter();
+        let first = match types.next() {
+            Some(first) => first,
+            None => return,
+        };
+        if let Some(other) = types.find(|ty| unify(first, ty).is_none()) {
+            let message = format!(
+                "The branches of this 'if' give values of different types, '{}' and '{}'",
+                type_name(first),
+                type_name(other)
+            );
+            self.throw_error(message);
+        }
+    }
+
+    /// The types of the values the branches of an `if` with an `else` give,
+    /// for the ones that are known
+    fn branch_types(&mut self, value: &Expression) -> Vec<Type> {
+        let (then_branch, else_branch) = match value {
+            Expression::If {
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => (Expression::Block(then_branch.clone()), else_branch),
+            _ => return Vec::new(),
+        };
+
+        let mut types = Vec::new();
+        for branch in [&then_branch, else_branch.as_ref()] {
+            match self.quiet_type(branch) {
+                Some(ty) => types.push(ty),
+                None => types.extend(self.branch_types(branch)),
+            }
+        }
+        types
+    }
+
+    /// The type of a value that was already checked, without reporting its
+    /// problems a second time
+    fn quiet_type(&mut self, value: &Expression) -> Option<Type> {
+        let found = (self.errors.len(), self.bindings.len(), self.lints.len());
+        let ty = self.check_expr(value);
+        self.errors.truncate(found.0);
+        self.bindings.truncate(found.1);
+        self.lints.truncate(found.2);
+        ty
+    }
+
+    /// Reports `value`, of type `ty`, when it may be null but is about to be
+    /// `action`
+    fn expect_present(&mut self, value: &Expression, ty: &Option<Type>, action: &str) {
+        if ty.as_ref().is_some_and(Type::is_optional) {
+            let message = format!(
+                "{} may be null, so it can't be {}. Check it against null or give it a default with '??' first",
+                describe(value),
+                action
+            );
+            self.throw_error(message);
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: Option<Type>, params: Option<Vec<Param>>) {
+        self.declare_variable(name, ty, Origin::Implicit, params);
+    }
+
+    fn declare_variable(
+        &mut self,
+        name: &str,
+        ty: Option<Type>,
+        origin: Origin,
+        params: Option<Vec<Param>>,
+    ) {
+        let var = Variable {
+            declared: ty.clone(),
+            current: ty,
+            origin,
+            params,
+            narrowed: false,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), var);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Runs `check` with errors reported at `line`
+    fn at_line<T>(&mut self, line: usize, check: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.line, line);
+        let result = check(self);
+        self.line = outer;
+        result
+    }
+
+    fn throw_error(&mut self, message: String) {
+        let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
+        self.errors
+            .push(VeloError::error(self.line, &message, TypeError));
+    }
+}
+
+/// The integer a literal like `5` or `-5` stands for
+fn literal_integer(value: &Expression) -> Option<i64> {
+    match value {
+        Expression::Int(num) => Some(*num as i64),
+        Expression::Large(num) => Some(*num),
+        Expression::UnaryOp {
+            op: TokenType::Sub,
+            expr,
+        } => literal_integer(expr).map(|num| num.wrapping_neg()),
+        _ => None,
+    }
+}
+
+/// The items of an array or map literal given to a collection of type `ty`,
+/// each with the type it has to be and what kind of item it is
+fn literal_items<'a>(
+    value: &'a Expression,
+    ty: &'a Type,
+) -> Option<Vec<(&'a Expression, &'a Type, &'static str)>> {
+    let items = match (value, ty.non_optional()) {
+        (Expression::Array(items), Type::Array(item_ty)) => items
+            .iter()
+            .map(|item| (item, &**item_ty, "an item"))
+            .collect(),
+        (Expression::Map(entries), Type::Map(key_ty, value_ty)) => entries
+            .iter()
+            .flat_map(|(key, value)| [(key, &**key_ty, "a key"), (value, &**value_ty, "a value")])
+            .collect(),
+        _ => return None,
+    };
+    Some(items)
+}
+
+/// How a value is referred to in error messages
+fn describe(value: &Expression) -> String {
+    match value {
+        Expression::Identifier(name) => format!("'{}'", name),
+        Expression::CallExpr { name, .. } => format!("The result of '{}'", name),
+        _ => "This value".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+    use crate::syntax::parse::Parser;
+
+    fn check_source(source: &str) -> Result<Checked, Vec<VeloError>> {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        let nodes = Parser::new(&tokens)
+            .parse()
+            .unwrap_or_else(|errors| panic!("failed to parse {:?}: {:?}", source, errors));
+        check(&nodes, None)
+    }
+
+    /// The messages of the errors the checker finds in `source`
+    fn errors(source: &str) -> Vec<String> {
+        match check_source(source) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.message).collect(),
+        }
+    }
+
+    /// The messages of the warnings the checker finds in `source`
+    fn warnings(source: &str) -> Vec<String> {
+        match check_source(source) {
+            Ok(checked) => checked.warnings.into_iter().map(|w| w.message).collect(),
+            Err(errors) => panic!("failed to check {:?}: {:?}", source, errors),
+        }
+    }
+
+    /// The lines of the `null_comparison` warnings in `source`
+    fn null_comparisons(source: &str) -> Vec<usize> {
+        match check_source(source) {
+            Ok(checked) => checked.lints.iter().map(|lint| lint.line).collect(),
+            Err(errors) => panic!("failed to check {:?}: {:?}", source, errors),
+        }
+    }
+
+    fn assert_error(source: &str, expected: &str) {
+        let errors = errors(source);
+        assert!(
+            errors.iter().any(|error| error.contains(expected)),
+            "expected an error containing {:?} in {:?}, found {:?}",
+            expected,
+            source,
+            errors
+        );
+    }
+
+    fn assert_ok(source: &str) {
+        let errors = errors(source);
+        assert!(
+            errors.is_empty(),
+            "unexpected errors in {:?}: {:?}",
+            source,
+            errors
+        );
+    }
+
+    #[test]
+    fn mixed_array_given_to_a_typed_variable() {
+        assert_error(
+            "k: [int] := [1, \"a\"];",
+            "variable 'k' is declared as '[int]' but its value holds an item of type 'string'",
+        );
+    }
+
+    #[test]
+    fn mixed_array_given_to_a_typed_parameter() {
+        assert_error(
+            "fun s(xs [int]) { } s([1, \"b\"]);",
+            "parameter 'xs' of function 's' is of type '[int]' but the argument holds an item of type 'string'",
+        );
+    }
+
+    #[test]
+    fn mixed_array_inside_a_nested_array() {
+        assert_error(
+            "n: [[int]] := [[1], [1, true]];",
+            "holds an item of type 'bool'",
+        );
+    }
+
+    #[test]
+    fn mixed_map_given_to_a_typed_variable() {
+        assert_error(
+            "m: map<string, int> := {\"a\": 1, 2: \"b\"};",
+            "holds a key of type 'int'",
+        );
+        assert_error(
+            "m: map<string, int> := {\"a\": 1, \"b\": \"c\"};",
+            "holds a value of type 'string'",
+        );
+    }
+
+    #[test]
+    fn mixed_arrays_without_a_type_are_allowed() {
+        assert_ok("xs := [1, \"a\"]; println(xs);");
+        assert_ok("fun size<T>(xs [T]) -> int { 0 } println(size([1, \"a\"]));");
+        assert_ok("xs: [int?] := [1, null]; println(xs);");
+    }
+
+    #[test]
+    fn items_of_a_mixed_array_are_only_reported_once() {
+        let errors = errors("k: [int] := [1, \"a\", !2];");
+        let not = errors.iter().filter(|error| error.contains("'!'")).count();
+        assert_eq!(not, 1, "{:?}", errors);
+    }
+
+    #[test]
+    fn generic_map_parameters_bind_their_keys_and_values() {
+        let get = "fun get<K, V>(m map<K, V>, k K) -> V { m[k] }";
+        assert_ok(&format!(
+            "{} x: int := get({{\"a\": 1}}, \"a\"); println(x);",
+            get
+        ));
+        assert_error(
+            &format!("{} x: string := get({{\"a\": 1}}, \"a\"); println(x);", get),
+            "Mismatched types",
+        );
+        assert_error(
+            &format!("{} println(get({{\"a\": 1}}, 2));", get),
+            "parameter 'k' of function 'get' is of type 'string'",
+        );
+    }
+
+    #[test]
+    fn integers_only_widen_without_a_cast() {
+        assert_ok("s: short := 1; l: large := s; i: int := s; println(l, i);");
+        assert_ok("l: large := 5; t: short := l as short; println(t);");
+        assert_error(
+            "l: large := 5000000000; t: short := l;",
+            "variable 't' is declared as 'short' but its value is of type 'large'",
+        );
+        assert_error(
+            "s: short := 1; i := 40000; s = i;",
+            "variable 's' is declared as 'short' but its value is of type 'int'",
+        );
+        assert_error(
+            "fun f(a short) { } i := 1; f(i);",
+            "parameter 'a' of function 'f' is of type 'short'",
+        );
+    }
+
+    #[test]
+    fn literals_are_stored_as_any_integer_type_they_fit_in() {
+        assert_ok("s: short := 5; xs: [short] := [1, -2]; println(s, xs);");
+        assert_ok("m: map<short, large> := {1: 2}; println(m);");
+        assert_error("s: short := 40000;", "40000 doesn't fit in it");
+        assert_error(
+            "xs: [short] := [1, 40000];",
+            "its value holds an item of type 'int'",
+        );
+    }
+
+    #[test]
+    fn loops_forget_null_checks_their_body_undoes() {
+        assert_error(
+            "i: int? := 1; if i != null { for _k in 0..2 { println(i + 1); i = null; } }",
+            "'i' may be null",
+        );
+        assert_error(
+            "i: int? := 1; if i != null { loop { println(i + 1); if true { i = null; } } }",
+            "'i' may be null",
+        );
+    }
+
+    #[test]
+    fn loops_keep_null_checks_their_body_leaves_alone() {
+        assert_ok("i: int? := 1; if i != null { for _k in 0..2 { println(i + 1); } }");
+        assert_ok("i: int? := 1; if i != null { for _k in 0..2 { println(i + 1); i = 2; } }");
+    }
+
+    #[test]
+    fn generic_bodies_only_know_their_type_parameters() {
+        assert_error(
+            "fun bad<T>(x T) -> int { x }",
+            "function 'bad' should return 'int' but the returned value is of type 'T'",
+        );
+        assert_error(
+            "fun bad<T>(x T) -> T { 5 }",
+            "function 'bad' should return 'T' but the returned value is of type 'int'",
+        );
+        assert_error(
+            "fun f(a int) { } fun bad<T>(x T) { f(x); }",
+            "parameter 'a' of function 'f' is of type 'int' but the argument is of type 'T'",
+        );
+        assert_ok("fun id<T>(x T) -> T { x } fun twice<T>(x T) -> [T] { [id(x), x] }");
+        assert_ok("fun show<T>(x T) { println(x); } show(1);");
+    }
+
+    #[test]
+    fn if_branches_have_to_agree() {
+        assert_error(
+            "fun h(b bool) -> int { if b { 1 } else { \"a\" } }",
+            "function 'h' should return 'int' but a branch of the 'if' gives a value of type 'string'",
+        );
+        assert_error(
+            "x := if true { 1 } else { \"a\" };",
+            "give values of different types, 'int' and 'string'",
+        );
+        assert_ok("x := if true { 1 } else { 2.5 }; y: int? := if true { 1 } else { null };");
+        assert_ok("if true { println(1) } else { 5 }");
+    }
+
+    #[test]
+    fn only_a_bare_fun_accepts_any_function() {
+        assert_error(
+            "fun run(cb fun() -> void) { cb(); } run(fun(x int) -> int { x });",
+            "parameter 'cb' of function 'run' is of type 'fun() -> void' but the argument is of type 'fun(int) -> int'",
+        );
+        assert_ok("fun run(cb fun) { } run(fun(x int) -> int { x });");
+        assert_ok("fun run(cb fun() -> void) { cb(); } run(fun() -> int { 1 });");
+    }
+
+    #[test]
+    fn named_and_default_arguments_are_matched_to_parameters() {
+        let greet = "fun greet(name string, greeting string = \"hi\", times int = 1) {}";
+        assert_ok(&format!("{} greet(\"a\", greeting: \"yo\");", greet));
+        assert_ok(&format!("{} greet(times: 2, name: \"b\");", greet));
+        assert_error(
+            &format!("{} greet(\"a\", nme: \"x\");", greet),
+            "Function 'greet' has no parameter named 'nme'",
+        );
+        assert_error(
+            &format!("{} greet(\"a\", name: \"b\");", greet),
+            "Parameter 'name' of function 'greet' was given more than one argument",
+        );
+        assert_error(
+            &format!("{} greet(times: 2);", greet),
+            "Missing argument for parameter 'name' in call to function 'greet'",
+        );
+        assert_error(
+            &format!("{} greet(\"a\", times: \"x\");", greet),
+            "parameter 'times' of function 'greet' is of type 'int'",
+        );
+    }
+
+    #[test]
+    fn default_values_have_to_fit_their_parameter() {
+        assert_error(
+            "fun f(x int = \"s\") {}",
+            "its default value is of type 'string'",
+        );
+        assert_error(
+            "println(greeting: 1);",
+            "Library function 'println' doesn't take named arguments",
+        );
+    }
+
+    #[test]
+    fn calls_on_any_expression_are_checked() {
+        let adder = "fun adder(x int) -> fun(int) -> int { fun(y int) -> int { x + y } }";
+        assert_ok(&format!("{} z: int := adder(2)(3);", adder));
+        assert_error(
+            &format!("{} adder(2)(\"a\");", adder),
+            "parameter '#1' of function '<function>' is of type 'int'",
+        );
+        assert_error(
+            "xs := [1, 2]; xs[0](1);",
+            "This value is not a function, it's a value of type 'int'",
+        );
+    }
+
+    #[test]
+    fn built_ins_are_values() {
+        assert_ok("fun apply(f fun(int) -> void, x int) { f(x); } apply(print, 1);");
+        assert_ok("p := println; p(1, 2);");
+        assert_ok("import std::math; sq := math::sqrt; println(sq(4));");
+    }
+
+    #[test]
+    fn comparing_a_value_that_is_never_null_to_null() {
+        assert_eq!(null_comparisons("x := 1;\nif x == null { }"), vec![2]);
+        assert_eq!(
+            null_comparisons("fun f(s string) -> bool { null != s }"),
+            vec![1]
+        );
+        assert!(null_comparisons("x: int? := null; if x == null { }").is_empty());
+        assert!(null_comparisons("fun f<T>(t T) -> bool { t == null }").is_empty());
+    }
+
+    #[test]
+    fn allow_only_covers_the_statement_after_it() {
+        let found = warnings("@allow(unused_variables) x := 1; y := 2;");
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert!(found[0].contains("Variable 'y' is never used"));
+    }
+
+    #[test]
+    fn a_value_that_always_returns_ends_the_function() {
+        assert_ok("fun k() -> int { _x := if true { return 1; } else { return 2; }; }");
+        assert_ok(
+            "fun k(b bool) -> int { x := 0; x = if b { return 1; } else { panic(\"no\") }; }",
+        );
+        assert_error(
+            "fun k(b bool) -> int { _x := if b { return 1; } else { 2 }; }",
+            "can reach the end",
+        );
+    }
+
+    #[test]
+    fn ranges_are_made_of_numbers() {
+        assert_ok("r := 1..4 step 2; xs := [1, 2, 3][r]; println(xs, \"abc\"[0..=1]);");
+        assert_error(
+            "r := 1..\"a\";",
+            "Ranges can only be made of numbers, found a value of type 'string'",
+        );
+        assert_error(
+            "for i in 1..3 { _x: string := i; }",
+            "is declared as 'string' but its value is of type 'int'",
+        );
+    }
+
+    #[test]
+    fn try_gives_the_type_of_both_blocks() {
+        assert_ok("x: int := try { 1 } catch { 2 }; println(x);");
+        assert_error(
+            "try { } catch e { _x: int := e; }",
+            "variable '_x' is declared as 'int' but its value is of type 'string'",
+        );
+        assert_error(
+            "x: string := try { 1 } catch { 2 };",
+            "variable 'x' is declared as 'string' but its value is of type 'int'",
+        );
+    }
+
+    #[test]
+    fn allow_covers_everything_inside_the_statement() {
+        assert!(warnings("@allow(unused_variables)\nfun f() { x := 1; }\nf();").is_empty());
+        assert!(
+            warnings("@allow(unreachable_code)\nfun f() { return; println(1); }\nf();").is_empty()
+        );
+        let found = warnings("@allow(unused_variables)\nfun f() { return; println(1); }\nf();");
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert!(found[0].contains("can never run"), "{:?}", found);
+    }
+
+    #[test]
+    fn xor_takes_integers() {
+        assert_ok("x: int := 5 ^ 3; println(x);");
+        assert_error("println(true ^ 1);", "'^'");
+        assert_error("println(1.5 ^ 2);", "'^'");
+    }
+}
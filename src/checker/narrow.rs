@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use super::{Checker, Variable};
+use crate::syntax::ast::{Ast, Expression, FunctionBody};
+use crate::syntax::lexer::{TokenType, Type};
+
+/// Variables known not to be null, together with their narrowed types
+pub type Narrowed = Vec<(String, Type)>;
+
+impl Checker {
+    /// The optional variables `cond` proves aren't null when it's true and
+    /// when it's false, e.g. `x != null` narrows `x` when true
+    pub fn narrowing(&self, cond: &Expression) -> (Narrowed, Narrowed) {
+        let (lhs, op, rhs) = match cond {
+            Expression::UnaryOp {
+                op: TokenType::Not,
+                expr,
+            } => {
+                let (when_true, when_false) = self.narrowing(expr);
+                return (when_false, when_true);
+            }
+            Expression::BinaryOp { lhs, op, rhs, .. } => match (lhs.as_ref(), rhs.as_ref()) {
+                (Ast::Expression(lhs), Ast::Expression(rhs)) => (lhs, *op, rhs),
+                _ => return (Vec::new(), Vec::new()),
+            },
+            _ => return (Vec::new(), Vec::new()),
+        };
+
+        match op {
+            TokenType::And => {
+                let (mut when_true, _) = self.narrowing(lhs);
+                when_true.extend(self.narrowing(rhs).0);
+                (when_true, Vec::new())
+            }
+            TokenType::Or => {
+                let (_, mut when_false) = self.narrowing(lhs);
+                when_false.extend(self.narrowing(rhs).1);
+                (Vec::new(), when_false)
+            }
+            TokenType::EqEq | TokenType::Ne => {
+                let name = match (lhs, rhs) {
+                    (Expression::Identifier(name), Expression::Null)
+                    | (Expression::Null, Expression::Identifier(name)) => name,
+                    _ => return (Vec::new(), Vec::new()),
+                };
+                let narrowed = match self.lookup(name).and_then(|var| var.current.as_ref()) {
+                    Some(Type::Optional(ty)) => vec![(name.clone(), *ty.clone())],
+                    _ => Vec::new(),
+                };
+                match op {
+                    TokenType::Ne => (narrowed, Vec::new()),
+                    _ => (Vec::new(), narrowed),
+                }
+            }
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Gives the variables their narrowed types in the innermost scope
+    pub fn narrow(&mut self, narrowed: Narrowed) {
+        for (name, ty) in narrowed {
+            let var = match self.lookup(&name) {
+                Some(var) => Variable {
+                    current: Some(ty),
+                    narrowed: true,
+                    ..var.clone()
+                },
+                None => continue,
+            };
+            if let Some(scope) = self.scopes.last_mut() {
+                match scope.get_mut(&name) {
+                    Some(existing) => existing.current = var.current,
+                    None => {
+                        scope.insert(name, var);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the body of a loop once without reporting anything, so the null
+    /// checks it undoes are already forgotten when it's checked for real. The
+    /// rounds after the first start with what the one before left behind
+    pub fn widen_loop(&mut self, body: &FunctionBody) {
+        let found = (self.errors.len(), self.bindings.len(), self.lints.len());
+        self.scopes.push(HashMap::new());
+        self.check_body(body);
+        self.scopes.pop();
+        self.errors.truncate(found.0);
+        self.bindings.truncate(found.1);
+        self.lints.truncate(found.2);
+    }
+
+    /// Forgets every null check of `name` after it's assigned a value that may
+    /// be null, up to the scope it was declared in
+    pub fn widen(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(var) = scope.get_mut(name) {
+                var.current = var.declared.clone();
+                if !var.narrowed {
+                    return;
+                }
+            }
+        }
+    }
+}
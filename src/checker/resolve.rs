@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{
+    ErrorType::NameError, Lint, Severity, VeloError, ERROR_INDICATOR, WARNING_INDICATOR,
+};
+use crate::runtime::builtins::builtins;
+use crate::syntax::ast::{Ast, Expression, FunctionBody, Param, Statement};
+
+/// What a name was declared as
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Variable,
+    Parameter { function: String },
+    Function,
+    Module,
+}
+
+#[derive(Debug)]
+struct Declaration {
+    kind: Kind,
+    line: usize,
+    used: bool,
+    exported: bool,
+    /// Whether an `@allow(unused_variables)` covers its declaration
+    allowed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    declared: HashMap<String, Declaration>,
+    /// Names declared further down the block, together with whether they've
+    /// been used. Functions defined before them can still use them, since a
+    /// function looks its names up when it runs
+    later: HashMap<String, bool>,
+    /// Whether this is the scope of a function's parameters and body
+    function: bool,
+}
+
+/// Binds every name in a program to its declaration, reporting names that
+/// aren't declared or are declared twice as errors and variables that are
+/// never used as warnings
+pub fn resolve(nodes: &[Ast]) -> (Vec<VeloError>, Vec<VeloError>) {
+    let mut resolver = Resolver {
+        scopes: vec![Scope::default()],
+        line: 0,
+        allowed: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+    resolver.expect_later(nodes.iter().filter_map(|node| match node {
+        Ast::Statement(stmt) => Some(stmt),
+        Ast::Expression(_) => None,
+    }));
+
+    for node in nodes {
+        match node {
+            Ast::Expression(expr) => resolver.resolve_expr(expr),
+            Ast::Statement(stmt) => resolver.resolve_statement(stmt),
+        }
+    }
+    resolver.pop_scope();
+
+    // scopes don't keep their names in order
+    resolver
+        .warnings
+        .sort_by(|a, b| (a.line, &a.message).cmp(&(b.line, &b.message)));
+    (resolver.errors, resolver.warnings)
+}
+
+struct Resolver {
+    scopes: Vec<Scope>,
+    /// The line of the statement or expression being resolved
+    line: usize,
+    /// The lints turned off by the attributes of the statements around the
+    /// code being resolved
+    allowed: Vec<Lint>,
+    errors: Vec<VeloError>,
+    warnings: Vec<VeloError>,
+}
+
+impl Resolver {
+    fn resolve_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableAssignment {
+                name, value, line, ..
+            } => {
+                self.line = *line;
+                // the value can't refer to the variable it's being stored in
+                self.resolve_expr(value);
+                self.line = *line;
+                self.declare(name, Kind::Variable);
+            }
+            Statement::Reassignment { name, value, line } => {
+                self.line = *line;
+                self.resolve_expr(value);
+                self.line = *line;
+                // assigning to a variable doesn't count as using it
+                if !self.lookup(name, false) {
+                    let message = format!(
+                        "Cannot assign to '{}', it hasn't been declared. Declare it with ':=' first",
+                        name
+                    );
+                    self.throw_error(message);
+                }
+            }
+            Statement::IndexAssignment {
+                name,
+                indices,
+                value,
+                line,
+            } => {
+                self.line = *line;
+                self.use_name(name, "value");
+                for index in indices {
+                    self.resolve_expr(index);
+                }
+                self.resolve_expr(value);
+            }
+            Statement::Function {
+                name,
+                params,
+                body,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                // declared first, so it can call itself
+                self.declare(name, Kind::Function);
+                self.resolve_function(name, params, body);
+            }
+            Statement::Return { value, line } => {
+                self.line = *line;
+                self.resolve_expr(value);
+            }
+            Statement::For {
+                name,
+                value_name,
+                iterable,
+                body,
+                line,
+            } => {
+                self.line = *line;
+                self.resolve_expr(iterable);
+                self.scopes.push(Scope::default());
+                self.line = *line;
+                self.declare(name, Kind::Variable);
+                if let Some(value_name) = value_name {
+                    self.declare(value_name, Kind::Variable);
+                }
+                self.resolve_body(body);
+                self.pop_scope();
+            }
+            Statement::Loop { body, line } => {
+                self.line = *line;
+                self.resolve_block(body, None);
+            }
+            Statement::Break { .. } | Statement::Continue { .. } => {}
+            Statement::ImportPath {
+                path,
+                is_library,
+                line,
+            } => {
+                self.line = *line;
+                let name = match is_library {
+                    true => path.rsplit("::").next().unwrap_or(path).to_string(),
+                    false => Path::new(path)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_else(|| path.clone()),
+                };
+                self.declare(&name, Kind::Module);
+            }
+            Statement::Export(stmt) => {
+                self.resolve_statement(stmt);
+                let declared = stmt
+                    .declared_name()
+                    .and_then(|name| self.scopes.last_mut()?.declared.get_mut(name));
+                if let Some(declaration) = declared {
+                    declaration.exported = true;
+                }
+            }
+            Statement::Attributed { attribute, stmt } => {
+                let outer = self.allowed.len();
+                self.allowed.extend(attribute.allowed());
+                self.resolve_statement(stmt);
+                self.allowed.truncate(outer);
+            }
+            Statement::ExprStmt(expr) => self.resolve_expr(expr),
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &Expression) {
+        match expr {
+            // built ins are values too, unless a variable shadows them
+            Expression::Identifier(name)
+                if !self.lookup(name, true) && !builtins().iter().any(|f| f.name == *name) =>
+            {
+                self.use_name(name, "value")
+            }
+            Expression::Identifier(_) => {}
+            Expression::CallExpr { name, params, line } => {
+                self.line = *line;
+                for arg in params {
+                    self.resolve_expr(&arg.value);
+                }
+                self.line = *line;
+                // built ins are found before variables, unless the name
+                // belongs to a module
+                if !builtins().iter().any(|function| function.name == *name) {
+                    self.use_name(name, "function");
+                }
+            }
+            Expression::Call {
+                callee,
+                params,
+                line,
+            } => {
+                self.line = *line;
+                self.resolve_expr(callee);
+                for arg in params {
+                    self.line = *line;
+                    self.resolve_expr(&arg.value);
+                }
+            }
+            Expression::Lambda { params, body, .. } => {
+                self.resolve_function("<lambda>", params, body)
+            }
+            Expression::BinaryOp { lhs, rhs, line, .. } => {
+                for side in [lhs, rhs] {
+                    self.line = *line;
+                    if let Ast::Expression(side) = side.as_ref() {
+                        self.resolve_expr(side);
+                    }
+                }
+            }
+            Expression::UnaryOp { expr, .. } => self.resolve_expr(expr),
+            Expression::Cast { value, line, .. } => {
+                self.line = *line;
+                self.resolve_expr(value);
+            }
+            Expression::Range {
+                start, end, step, ..
+            } => {
+                self.resolve_expr(start);
+                self.resolve_expr(end);
+                if let Some(step) = step {
+                    self.resolve_expr(step);
+                }
+            }
+            Expression::Index {
+                target,
+                index,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                self.resolve_expr(target);
+                self.line = *line;
+                self.resolve_expr(index);
+            }
+            Expression::Array(items) => {
+                for item in items {
+                    self.resolve_expr(item);
+                }
+            }
+            Expression::Map(entries) => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expression::Block(body) => self.resolve_block(body, None),
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+                line,
+            } => {
+                self.line = *line;
+                self.resolve_expr(cond);
+                self.resolve_block(then_branch, None);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_expr(else_branch);
+                }
+            }
+            Expression::Try {
+                body,
+                error_name,
+                handler,
+            } => {
+                self.resolve_block(body, None);
+                self.resolve_block(handler, error_name.as_deref());
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_function(&mut self, name: &str, params: &[Param], body: &FunctionBody) {
+        self.scopes.push(Scope {
+            function: true,
+            ..Scope::default()
+        });
+        let line = self.line;
+        for param in params {
+            // defaults can refer to the parameters before them
+            if let Some(default) = &param.default {
+                self.resolve_expr(default);
+            }
+            self.line = line;
+            let kind = Kind::Parameter {
+                function: name.to_string(),
+            };
+            self.declare(&param.name, kind);
+        }
+        self.resolve_body(body);
+        self.pop_scope();
+    }
+
+    /// Resolves a block in a scope of its own, with `binding` declared in it
+    /// first, like the error of a `catch`
+    fn resolve_block(&mut self, body: &FunctionBody, binding: Option<&str>) {
+        self.scopes.push(Scope::default());
+        if let Some(binding) = binding {
+            self.declare(binding, Kind::Variable);
+        }
+        self.resolve_body(body);
+        self.pop_scope();
+    }
+
+    /// Resolves the statements of a block in the current scope
+    fn resolve_body(&mut self, body: &FunctionBody) {
+        self.expect_later(body.stmts.iter());
+        for stmt in &body.stmts {
+            self.resolve_statement(stmt);
+        }
+        if let Some(value) = &body.value {
+            self.resolve_expr(value);
+        }
+    }
+
+    /// Notes the names a block declares before any of it is resolved
+    fn expect_later<'a>(&mut self, stmts: impl Iterator<Item = &'a Statement>) {
+        let names: Vec<String> = stmts
+            .filter_map(|stmt| stmt.declared_name())
+            .map(str::to_string)
+            .collect();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope
+                .later
+                .extend(names.into_iter().map(|name| (name, false)));
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: Kind) {
+        let line = self.line;
+        if kind == Kind::Variable && !name.starts_with('_') {
+            self.warn_shadowing(name);
+        }
+
+        let scope = match self.scopes.last_mut() {
+            Some(scope) => scope,
+            None => return,
+        };
+
+        if let Some(existing) = scope.declared.get(name) {
+            let message = match kind {
+                Kind::Module => format!("A module named '{}' has already been imported", name),
+                _ => format!(
+                    "'{}' is already declared in this scope, on line {}",
+                    name, existing.line
+                ),
+            };
+            self.throw_error(message);
+            return;
+        }
+
+        // a function defined earlier may have used it already
+        let used = scope.later.remove(name).unwrap_or(false);
+        let declaration = Declaration {
+            kind,
+            line,
+            used,
+            exported: false,
+            allowed: self.allowed.contains(&Lint::UnusedVariables),
+        };
+        scope.declared.insert(name.to_string(), declaration);
+    }
+
+    /// Warns about a variable declared in a block with the same name as one
+    /// outside of it, in the same function. Parameters and variables of a
+    /// function may take any name from outside of it
+    fn warn_shadowing(&mut self, name: &str) {
+        // declaring it twice in the same block is an error instead
+        let outer = match self.scopes.split_last() {
+            Some((current, _)) if current.function || current.declared.contains_key(name) => return,
+            Some((_, outer)) => outer,
+            None => return,
+        };
+
+        let mut shadowed = None;
+        for scope in outer.iter().rev() {
+            if let Some(declaration) = scope.declared.get(name) {
+                shadowed = Some(declaration.line).filter(|_| declaration.kind != Kind::Module);
+                break;
+            }
+            if scope.function {
+                break;
+            }
+        }
+
+        if let Some(shadowed) = shadowed.filter(|_| !self.allowed.contains(&Lint::Shadowing)) {
+            let message = format!(
+                "{} \x1b[1mVariable '{}' shadows one declared outside of this block\x1b[0m",
+                WARNING_INDICATOR, name
+            );
+            let warning = VeloError::warning(self.line, &message, NameError, Lint::Shadowing)
+                .with_note(
+                    Severity::Note,
+                    format!("'{}' was first declared on line {}", name, shadowed),
+                )
+                .with_note(
+                    Severity::Help,
+                    "give it a different name to keep both apart".to_string(),
+                );
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Finds the declaration `name` refers to, marking it as used if `used`
+    fn lookup(&mut self, name: &str, used: bool) -> bool {
+        // names declared further down can only be used from inside a
+        // function, which runs after they're declared
+        let mut in_function = false;
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(declaration) = scope.declared.get_mut(name) {
+                declaration.used |= used;
+                return true;
+            }
+            if let (Some(later), true) = (scope.later.get_mut(name), in_function) {
+                *later |= used;
+                return true;
+            }
+            in_function |= scope.function;
+        }
+        false
+    }
+
+    /// Binds a use of `name` to its declaration, reporting it when there's
+    /// none. `what` is what the name is expected to be
+    fn use_name(&mut self, name: &str, what: &str) {
+        // members of a module are only known once it's loaded
+        let (name, module) = match name.split_once("::") {
+            Some((module, _)) => (module, true),
+            None => (name, false),
+        };
+        if self.lookup(name, true) {
+            return;
+        }
+
+        let message = match module {
+            true => format!(
+                "Cannot find module '{}', did you forget to import it?",
+                name
+            ),
+            false => format!("Cannot find {} '{}' in scope", what, name),
+        };
+        self.throw_error(message);
+    }
+
+    /// Leaves the innermost scope, reporting the variables in it that were
+    /// never used
+    fn pop_scope(&mut self) {
+        let scope = match self.scopes.pop() {
+            Some(scope) => scope,
+            None => return,
+        };
+
+        for (name, declaration) in scope.declared {
+            if declaration.used
+                || declaration.exported
+                || declaration.allowed
+                || name.starts_with('_')
+            {
+                continue;
+            }
+            let message = match declaration.kind {
+                Kind::Variable => format!("Variable '{}' is never used", name),
+                Kind::Parameter { function } => {
+                    format!(
+                        "Parameter '{}' of function '{}' is never used",
+                        name, function
+                    )
+                }
+                Kind::Function | Kind::Module => continue,
+            };
+            let message = format!("{} \x1b[1m{}\x1b[0m", WARNING_INDICATOR, message);
+            let warning =
+                VeloError::warning(declaration.line, &message, NameError, Lint::UnusedVariables)
+                    .with_note(
+                        Severity::Help,
+                        format!(
+                            "start its name with '_' if that's intended, i.e. '_{}'",
+                            name
+                        ),
+                    );
+            self.warnings.push(warning);
+        }
+    }
+
+    fn throw_error(&mut self, message: String) {
+        let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
+        self.errors
+            .push(VeloError::error(self.line, &message, NameError));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+    use crate::syntax::parse::Parser;
+
+    /// The lines and messages of errors or warnings
+    type Found = Vec<(usize, String)>;
+
+    /// The errors and the warnings found resolving `source`
+    fn resolved(source: &str) -> (Found, Found) {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        let nodes = Parser::new(&tokens)
+            .parse()
+            .unwrap_or_else(|errors| panic!("failed to parse {:?}: {:?}", source, errors));
+        let (errors, warnings) = resolve(&nodes);
+        let found = |errors: Vec<VeloError>| {
+            errors
+                .into_iter()
+                .map(|error| (error.line, error.message))
+                .collect()
+        };
+        (found(errors), found(warnings))
+    }
+
+    fn assert_found(found: &[(usize, String)], line: usize, expected: &str) {
+        assert!(
+            found
+                .iter()
+                .any(|(l, message)| *l == line && message.contains(expected)),
+            "expected {:?} on line {}, found {:?}",
+            expected,
+            line,
+            found
+        );
+    }
+
+    #[test]
+    fn names_have_to_be_declared_before_they_are_used() {
+        let (errors, _) = resolved("println(y);\ny := 1;\nz = 2;\nprintln(math::pi);");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_found(&errors, 1, "Cannot find value 'y' in scope");
+        assert_found(&errors, 3, "Cannot assign to 'z', it hasn't been declared");
+        assert_found(
+            &errors,
+            4,
+            "Cannot find module 'math', did you forget to import it?",
+        );
+    }
+
+    #[test]
+    fn functions_can_use_names_declared_after_them() {
+        let (errors, warnings) =
+            resolved("fun g() { h(); println(n); }\nfun h() {}\nn := 1;\ng();");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn names_are_declared_once_per_scope() {
+        let (errors, _) = resolved("x := 1;\nx := 2;\nprintln(x);");
+        assert_found(
+            &errors,
+            2,
+            "'x' is already declared in this scope, on line 1",
+        );
+
+        let (errors, warnings) =
+            resolved("w := 1;\nif true {\n  w := 2;\n  println(w);\n}\nprintln(w);");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_found(
+            &warnings,
+            3,
+            "Variable 'w' shadows one declared outside of this block",
+        );
+    }
+
+    #[test]
+    fn unused_variables_and_parameters_are_warned_about() {
+        let (_, warnings) = resolved("fun f(a int, _b int) {\n  q := 1;\n}\nf(1, 2);\n_r := 1;");
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert_found(&warnings, 1, "Parameter 'a' of function 'f' is never used");
+        assert_found(&warnings, 2, "Variable 'q' is never used");
+    }
+
+    #[test]
+    fn exports_and_built_ins_count_as_used() {
+        let (errors, warnings) = resolved("export x := 1;\nlog := println;\nlog(x);");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+}
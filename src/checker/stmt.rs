@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use super::flow;
+use super::types::{iterated_types, null_type, type_name};
+use super::{Binding, Checker, Origin, Target};
+use crate::runtime::builtins::library;
+use crate::syntax::ast::{Expression, FunctionBody, Param, Statement};
+use crate::syntax::lexer::Type;
+
+impl Checker {
+    pub fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableAssignment {
+                name,
+                ty,
+                value,
+                line,
+                ..
+            } => self.at_line(*line, |checker| {
+                let value_ty = checker.check_expr(value);
+
+                // `input` is converted to the type of the variable it's stored in
+                let from_input =
+                    matches!(value, Expression::CallExpr { name, .. } if name == "input");
+                if let (Some(ty), false) = (ty, from_input) {
+                    checker.expect_type(value, &value_ty, ty, Target::Variable(name));
+                }
+                if let (None, None) = (ty, &value_ty) {
+                    checker.expect_same_branches(value);
+                }
+
+                let params = match value {
+                    Expression::Lambda { params, .. } => Some(params.clone()),
+                    _ => None,
+                };
+                let origin = match ty {
+                    Some(_) => Origin::Annotated,
+                    None => Origin::Inferred,
+                };
+                let ty = ty.clone().or(value_ty);
+                checker.bindings.push(Binding {
+                    name: name.clone(),
+                    line: *line,
+                    ty: ty.clone(),
+                });
+                checker.declare_variable(name, ty, origin, params);
+            }),
+            Statement::Reassignment { name, value, line } => self.at_line(*line, |checker| {
+                let value_ty = checker.check_expr(value);
+                let var = checker.lookup(name).cloned();
+                match var.as_ref().map(|var| (var.origin, &var.declared)) {
+                    Some((Origin::Annotated, Some(declared))) => {
+                        checker.expect_type(value, &value_ty, declared, Target::Variable(name))
+                    }
+                    // a variable that started out as `null` could be meant for
+                    // anything
+                    Some((Origin::Inferred, Some(declared))) if *declared != null_type() => {
+                        checker.expect_type(value, &value_ty, declared, Target::Inferred(name))
+                    }
+                    _ => {}
+                }
+                if value_ty.as_ref().is_none_or(Type::is_optional) {
+                    checker.widen(name);
+                }
+            }),
+            Statement::IndexAssignment {
+                name,
+                indices,
+                value,
+                line,
+            } => self.at_line(*line, |checker| {
+                let target = Expression::Identifier(name.clone());
+                let mut item_ty = checker.check_expr(&target);
+                for index in indices {
+                    item_ty = checker.check_index(&target, item_ty, index, false);
+                }
+
+                let value_ty = checker.check_expr(value);
+                if let Some(item_ty) = item_ty {
+                    checker.expect_type(value, &value_ty, &item_ty, Target::Item(name));
+                }
+            }),
+            Statement::Function {
+                name,
+                params,
+                body,
+                ret_type,
+                line,
+                ..
+            } => self.at_line(*line, |checker| {
+                checker.check_function(name, params, body, ret_type)
+            }),
+            Statement::Return { value, line } => self.at_line(*line, |checker| {
+                let value_ty = checker.check_expr(value);
+                if let Some((function, ret_type)) = checker.functions.last().cloned() {
+                    if ret_type != Type::Void {
+                        checker.expect_type(value, &value_ty, &ret_type, Target::Return(&function));
+                    }
+                }
+            }),
+            Statement::For {
+                name,
+                value_name,
+                iterable,
+                body,
+                line,
+            } => self.at_line(*line, |checker| {
+                let iterable_ty = checker.check_expr(iterable);
+                checker.expect_present(iterable, &iterable_ty, "iterated over");
+
+                let (key_ty, item_ty) = match iterable_ty.as_ref().map(Type::non_optional) {
+                    Some(ty) => match iterated_types(ty) {
+                        Some((key, item)) => (Some(key), Some(item)),
+                        None => {
+                            let message =
+                                format!("Cannot iterate over a value of type '{}'", type_name(ty));
+                            checker.throw_error(message);
+                            (None, None)
+                        }
+                    },
+                    None => (None, None),
+                };
+
+                checker.scopes.push(HashMap::new());
+                match value_name {
+                    Some(value_name) => {
+                        checker.declare(name, key_ty, None);
+                        checker.declare(value_name, item_ty, None);
+                    }
+                    // a map on its own goes over its keys
+                    None if matches!(iterable_ty, Some(Type::Map(..))) => {
+                        checker.declare(name, key_ty, None)
+                    }
+                    None => checker.declare(name, item_ty, None),
+                }
+                checker.widen_loop(body);
+                checker.check_body(body);
+                checker.scopes.pop();
+            }),
+            Statement::Loop { body, .. } => {
+                self.scopes.push(HashMap::new());
+                self.widen_loop(body);
+                self.check_body(body);
+                self.scopes.pop();
+            }
+            Statement::Break { .. } | Statement::Continue { .. } => {}
+            Statement::Export(stmt) => self.check_statement(stmt),
+            Statement::Attributed { attribute, stmt } => {
+                let outer = self.allowed.len();
+                self.allowed.extend(attribute.allowed());
+                self.check_statement(stmt);
+                self.allowed.truncate(outer);
+            }
+            Statement::ExprStmt(expr) => {
+                self.check_expr(expr);
+            }
+            Statement::ImportPath {
+                path,
+                is_library: true,
+                ..
+            } => {
+                if let Some((_, functions)) = library(path) {
+                    let name = path.rsplit("::").next().unwrap_or(path);
+                    self.modules.insert(name.to_string(), functions);
+                }
+            }
+            Statement::ImportPath { .. } => {}
+        }
+    }
+
+    pub fn check_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        body: &FunctionBody,
+        ret_type: &Type,
+    ) {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            if let Some(default) = &param.default {
+                let default_ty = self.check_expr(default);
+                let target = Target::Default {
+                    name: &param.name,
+                    function: name,
+                };
+                self.expect_type(default, &default_ty, &param.ty, target);
+            }
+            let ty = Some(param.binding_type());
+            self.declare_variable(&param.name, ty, Origin::Annotated, None);
+        }
+
+        self.functions.push((name.to_string(), ret_type.clone()));
+        let value_ty = self.check_body(body);
+        if let (Some(value), false) = (&body.value, *ret_type == Type::Void) {
+            self.expect_type(value, &value_ty, ret_type, Target::Return(name));
+        }
+        self.functions.pop();
+        self.scopes.pop();
+    }
+
+    /// Checks the statements of a block in the current scope, returning the
+    /// type of its trailing value
+    pub fn check_body(&mut self, body: &FunctionBody) -> Option<Type> {
+        self.declare_functions(body.stmts.iter());
+
+        for stmt in &body.stmts {
+            self.check_statement(stmt);
+
+            // after `if x == null { return; }` the rest of the block knows x
+            // isn't null, the same goes for `break`, `continue` and `panic`
+            if let Statement::ExprStmt(Expression::If {
+                cond,
+                then_branch,
+                else_branch: None,
+                ..
+            }) = stmt
+            {
+                if flow::body_exit(then_branch).is_some() {
+                    let (_, when_false) = self.narrowing(cond);
+                    self.narrow(when_false);
+                }
+            }
+        }
+
+        match &body.value {
+            Some(value) => self.check_expr(value),
+            None => None,
+        }
+    }
+
+    /// Declares the functions of a block up front, so they can be called
+    /// before the statement declaring them has been checked
+    pub fn declare_functions<'a>(&mut self, stmts: impl Iterator<Item = &'a Statement>) {
+        for stmt in stmts {
+            let stmt = match stmt.unattributed() {
+                Statement::Export(stmt) => stmt.unattributed(),
+                stmt => stmt,
+            };
+            if let Statement::Function {
+                name,
+                params,
+                ret_type,
+                ..
+            } = stmt
+            {
+                let ty = Type::Function {
+                    params: params.iter().map(Param::binding_type).collect(),
+                    ret: Box::new(ret_type.clone()),
+                };
+                self.declare(name, Some(ty), Some(params.clone()));
+            }
+        }
+    }
+}
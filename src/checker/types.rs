@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::syntax::lexer::Type;
+
+/// The type of `null` itself
+pub fn null_type() -> Type {
+    Type::Optional(Box::new(Type::Void))
+}
+
+/// How a type is shown in error messages
+pub fn type_name(ty: &Type) -> String {
+    match *ty == null_type() {
+        true => "null".to_string(),
+        false => ty.to_string(),
+    }
+}
+
+pub fn is_integer(ty: &Type) -> bool {
+    matches!(ty, Type::Short | Type::Int | Type::Large)
+}
+
+pub fn is_numeric(ty: &Type) -> bool {
+    is_integer(ty) || *ty == Type::Float
+}
+
+/// Whether a value of type `value` can be used where a `target` is expected.
+/// Type parameters in `target` are bound to the first type given for them in
+/// `bindings`, every later use has to agree. See `rigid` for the type
+/// parameters of the function being checked
+pub fn accepts(target: &Type, value: &Type, bindings: &mut HashMap<String, Type>) -> bool {
+    match (target, value) {
+        (Type::Param(name), value) => match bindings.get(name).cloned() {
+            Some(Type::Param(bound)) if bound == *name => match value {
+                Type::Param(value) => value == name,
+                value => *value == Type::Any,
+            },
+            Some(bound) => accepts(&bound, value, bindings),
+            None => {
+                bindings.insert(name.clone(), value.clone());
+                true
+            }
+        },
+        (Type::Any, _) | (_, Type::Any) => true,
+        // the value of a type parameter could be of any type, so only the
+        // same parameter accepts it
+        (_, Type::Param(_)) => false,
+        (Type::Optional(_), value) if *value == null_type() => true,
+        (Type::Optional(target), Type::Optional(value)) => accepts(target, value, bindings),
+        (Type::Optional(target), value) => accepts(target, value, bindings),
+        (_, Type::Optional(_)) => false,
+        // whole numbers can be stored as floats, and integer types accept the
+        // ones no wider than themselves. Narrowing takes an `as`
+        (Type::Float, value) => is_numeric(value),
+        (target, value) if is_integer(target) => {
+            is_integer(value) && widest_number(target, value) == *target
+        }
+        (Type::Array(target), Type::Array(value)) => accepts(target, value, bindings),
+        (Type::Map(target_key, target_value), Type::Map(key, value)) => {
+            accepts(target_key, key, bindings) && accepts(target_value, value, bindings)
+        }
+        // a bare `fun` accepts any function, and could be any function, so
+        // it's only checked once it's called
+        (Type::AnyFunction, Type::Function { .. } | Type::AnyFunction)
+        | (Type::Function { .. }, Type::AnyFunction) => true,
+        (
+            Type::Function { params, ret },
+            Type::Function {
+                params: value_params,
+                ret: value_ret,
+            },
+        ) => {
+            params.len() == value_params.len()
+                // the function will be called with arguments of the target's
+                // parameter types
+                && params
+                    .iter()
+                    .zip(value_params)
+                    .all(|(param, value_param)| accepts(value_param, param, bindings))
+                && (**ret == Type::Void || accepts(ret, value_ret, bindings))
+        }
+        // there are no tuple values yet
+        (Type::Tuple(_), _) | (_, Type::Tuple(_)) => true,
+        (target, value) => target == value,
+    }
+}
+
+/// Bindings for `accepts` that keep the type parameters in `target` as they
+/// are, for the function that declares them. They stand for a type only a
+/// call knows, so they only accept themselves
+pub fn rigid(target: &Type) -> HashMap<String, Type> {
+    fn collect(ty: &Type, bindings: &mut HashMap<String, Type>) {
+        match ty {
+            Type::Param(name) => {
+                bindings.insert(name.clone(), ty.clone());
+            }
+            Type::Array(ty) | Type::Optional(ty) => collect(ty, bindings),
+            Type::Map(key, value) => {
+                collect(key, bindings);
+                collect(value, bindings);
+            }
+            Type::Tuple(types) => types.iter().for_each(|ty| collect(ty, bindings)),
+            Type::Function { params, ret } => {
+                params.iter().for_each(|ty| collect(ty, bindings));
+                collect(ret, bindings);
+            }
+            _ => {}
+        }
+    }
+
+    let mut bindings = HashMap::new();
+    collect(target, &mut bindings);
+    bindings
+}
+
+/// The type of a value that is either an `a` or a `b`, e.g. the two branches
+/// of an `if`, or `None` when they have nothing in common
+pub fn unify(a: &Type, b: &Type) -> Option<Type> {
+    if a == b {
+        return Some(a.clone());
+    }
+
+    match (a, b) {
+        (null, ty) | (ty, null) if *null == null_type() => {
+            Some(Type::Optional(Box::new(ty.non_optional().clone())))
+        }
+        (Type::Optional(a), b) | (b, Type::Optional(a)) => {
+            Some(Type::Optional(Box::new(unify(a, b.non_optional())?)))
+        }
+        (a, b) if is_numeric(a) && is_numeric(b) => Some(widest_number(a, b)),
+        (Type::Array(a), Type::Array(b)) => Some(Type::Array(Box::new(unify(a, b)?))),
+        (Type::Map(a_key, a_value), Type::Map(b_key, b_value)) => Some(Type::Map(
+            Box::new(unify(a_key, b_key)?),
+            Box::new(unify(a_value, b_value)?),
+        )),
+        _ => None,
+    }
+}
+
+/// The type arithmetic on two numbers of types `a` and `b` results in
+/// Whether `value as ty` is allowed for a value of type `from`: numbers
+/// convert between each other, anything becomes a string, and strings can be
+/// parsed as numbers and bools
+pub fn castable(from: &Type, ty: &Type) -> bool {
+    match (from, ty) {
+        (Type::Any, _) | (_, Type::String) => true,
+        (Type::String, ty) => is_numeric(ty) || *ty == Type::Bool,
+        (from, ty) => (is_numeric(from) && is_numeric(ty)) || from == ty,
+    }
+}
+
+pub fn widest_number(a: &Type, b: &Type) -> Type {
+    let rank = |ty: &Type| match ty {
+        Type::Short => 0,
+        Type::Int => 1,
+        Type::Large => 2,
+        _ => 3,
+    };
+    match rank(a) >= rank(b) {
+        true => a.clone(),
+        false => b.clone(),
+    }
+}
+
+/// The types of the items a `for` loop goes over, together with the type of
+/// the index or key that comes with each of them
+pub fn iterated_types(iterable: &Type) -> Option<(Type, Type)> {
+    match iterable {
+        Type::Array(item) => Some((Type::Int, *item.clone())),
+        Type::Map(key, value) => Some((*key.clone(), *value.clone())),
+        Type::String => Some((Type::Int, Type::String)),
+        Type::Range => Some((Type::Int, Type::Int)),
+        _ => None,
+    }
+}
@@ -1,17 +1,102 @@
 #![allow(dead_code)]
+use std::path::Path;
+
 pub const ERROR_INDICATOR: &str = "\x1b[1m[\x1b[0m\x1b[1;31merror\x1b[0m\x1b[1m]:\x1b[0m";
+pub const WARNING_INDICATOR: &str = "\x1b[1m[\x1b[0m\x1b[1;33mwarning\x1b[0m\x1b[1m]:\x1b[0m";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VeloError {
     pub line: usize,
     pub message: String,
     pub error_type: ErrorType,
+    pub severity: Severity,
+    /// The lint a warning comes from, which decides whether it's shown
+    pub lint: Option<Lint>,
+    /// Notes and help shown under the message
+    pub notes: Vec<(Severity, String)>,
+    /// The file the error is in, when it's an imported one rather than the
+    /// file being run or checked
+    pub file: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 pub enum ErrorType {
     ParseError,
     RuntimeError,
+    TypeError,
+    NameError,
+}
+
+/// How serious a diagnostic is. Errors stop the program from running,
+/// warnings don't, and notes and help only add to another diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Severity {
+    fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        }
+    }
+}
+
+/// A kind of warning, which can be turned off with `--allow <name>`,
+/// `@allow(name)` or in `velo.toml`. The ones after `UnreachableCode` are
+/// style lints, which only `velo lint` looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    UnusedVariables,
+    Shadowing,
+    UnreachableCode,
+    NamingConventions,
+    RedundantImmut,
+    NullComparison,
+    ShadowedBuiltins,
+    LongFunctions,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::UnusedVariables,
+        Lint::Shadowing,
+        Lint::UnreachableCode,
+        Lint::NamingConventions,
+        Lint::RedundantImmut,
+        Lint::NullComparison,
+        Lint::ShadowedBuiltins,
+        Lint::LongFunctions,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::Shadowing => "shadowing",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::NamingConventions => "naming_conventions",
+            Lint::RedundantImmut => "redundant_immut",
+            Lint::NullComparison => "null_comparison",
+            Lint::ShadowedBuiltins => "shadowed_builtins",
+            Lint::LongFunctions => "long_functions",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    /// The names of every lint, for error messages
+    pub fn names() -> String {
+        Lint::ALL.map(|lint| lint.name()).join(", ")
+    }
 }
 
 impl VeloError {
@@ -20,6 +105,181 @@ impl VeloError {
             line,
             message: message.to_string(),
             error_type,
+            severity: Severity::Error,
+            lint: None,
+            notes: Vec::new(),
+            file: None,
         }
     }
+
+    pub fn warning(line: usize, message: &str, error_type: ErrorType, lint: Lint) -> Self {
+        Self {
+            line,
+            message: message.to_string(),
+            error_type,
+            severity: Severity::Warning,
+            lint: Some(lint),
+            notes: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Adds a note or help line under the message
+    pub fn with_note(mut self, severity: Severity, note: String) -> Self {
+        self.notes.push((severity, note));
+        self
+    }
+
+    /// Notes that the error is in the imported file `file`, unless it's
+    /// already known to be in a file that one imports
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file.get_or_insert_with(|| file.display().to_string());
+        self
+    }
+}
+
+/// Which warnings are shown, and whether they stop the program like errors
+#[derive(Debug, Clone, Default)]
+pub struct WarningOptions {
+    /// Set by `--deny-warnings`
+    pub deny: bool,
+    /// Set by `--allow <lint>`, or `lint = "allow"` in `velo.toml`
+    pub allowed: Vec<Lint>,
+    /// Set by `lint = "deny"` in `velo.toml`
+    pub denied: Vec<Lint>,
+}
+
+impl WarningOptions {
+    /// Drops the warnings of allowed lints. Those turned off by an
+    /// `@allow(...)` are never reported in the first place. Denied warnings,
+    /// or all of them with `--deny-warnings`, become errors, in which case
+    /// everything left is returned as an error
+    pub fn apply(&self, warnings: Vec<VeloError>) -> Result<Vec<VeloError>, Vec<VeloError>> {
+        let warnings: Vec<VeloError> = warnings
+            .into_iter()
+            .filter(|warning| match warning.lint {
+                Some(lint) => !self.allowed.contains(&lint),
+                None => true,
+            })
+            .map(|warning| match warning.lint {
+                _ if self.deny => deny(
+                    warning,
+                    "warnings are errors because of '--deny-warnings'".to_string(),
+                ),
+                Some(lint) if self.denied.contains(&lint) => deny(
+                    warning,
+                    format!("'{}' is set to \"deny\" in velo.toml", lint.name()),
+                ),
+                _ => warning,
+            })
+            .collect();
+
+        match warnings
+            .iter()
+            .any(|warning| warning.severity == Severity::Error)
+        {
+            true => Err(warnings),
+            false => Ok(warnings),
+        }
+    }
+}
+
+/// Turns a warning into an error, noting why
+fn deny(warning: VeloError, reason: String) -> VeloError {
+    VeloError {
+        message: warning.message.replace(WARNING_INDICATOR, ERROR_INDICATOR),
+        severity: Severity::Error,
+        ..warning
+    }
+    .with_note(Severity::Note, reason)
+}
+
+pub fn report_errors(errors: &[VeloError], filename: &str) {
+    for error in errors {
+        println!("{}", error.message);
+        let filename = error.file.as_deref().unwrap_or(filename);
+        println!("  {}:{}", filename, error.line);
+        for (severity, note) in &error.notes {
+            println!("  \x1b[1m= {}:\x1b[0m {}", severity.label(), note);
+        }
+        println!("\n");
+
+        if let Some(lint) = error.lint {
+            println!(
+                "This warning can be turned off with '@allow({})' or '--allow {}'",
+                lint.name(),
+                lint.name()
+            );
+            continue;
+        }
+
+        println!("TODO: Potential Fixes");
+        match error.error_type {
+            ErrorType::ParseError => println!("This error is found to be of type 'ParseError'"),
+            ErrorType::RuntimeError => println!("This error is found to be of type 'RuntimeError'"),
+            ErrorType::TypeError => println!("This error is found to be of type 'TypeError'"),
+            ErrorType::NameError => println!("This error is found to be of type 'NameError'"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings() -> Vec<VeloError> {
+        vec![
+            VeloError::warning(1, "unused", ErrorType::NameError, Lint::UnusedVariables),
+            VeloError::warning(
+                2,
+                "unreachable",
+                ErrorType::NameError,
+                Lint::UnreachableCode,
+            ),
+        ]
+    }
+
+    #[test]
+    fn warnings_are_kept_by_default() {
+        let kept = WarningOptions::default().apply(warnings()).unwrap();
+        assert_eq!(kept, warnings());
+    }
+
+    #[test]
+    fn allowed_lints_are_dropped() {
+        let options = WarningOptions {
+            allowed: vec![Lint::UnusedVariables],
+            ..Default::default()
+        };
+        let kept = options.apply(warnings()).unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].lint, Some(Lint::UnreachableCode));
+    }
+
+    #[test]
+    fn deny_warnings_turns_every_warning_into_an_error() {
+        let options = WarningOptions {
+            deny: true,
+            allowed: vec![Lint::UnreachableCode],
+            ..Default::default()
+        };
+        let errors = options.apply(warnings()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert!(errors[0].notes[0].1.contains("--deny-warnings"));
+    }
+
+    #[test]
+    fn denied_lints_become_errors_and_keep_the_other_warnings() {
+        let options = WarningOptions {
+            denied: vec![Lint::UnreachableCode],
+            ..Default::default()
+        };
+        let errors = options.apply(warnings()).unwrap_err();
+        let severities: Vec<Severity> = errors.iter().map(|error| error.severity).collect();
+        assert_eq!(severities, [Severity::Warning, Severity::Error]);
+        assert!(errors[1].notes[0]
+            .1
+            .contains("'unreachable_code' is set to \"deny\""));
+    }
 }
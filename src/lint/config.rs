@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{ErrorType::ParseError, Lint, VeloError, WarningOptions, ERROR_INDICATOR};
+
+/// The name of the project file lints are configured in
+pub const CONFIG_FILE: &str = "velo.toml";
+
+/// How seriously a lint is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The `[lints]` section of `velo.toml`, where every lint can be set to
+/// `"allow"`, `"warn"` or `"deny"`, i.e. `shadowing = "allow"`
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    pub levels: Vec<(Lint, Level)>,
+    /// How long a function can get before `long_functions` warns about it
+    pub max_function_lines: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            levels: Vec::new(),
+            max_function_lines: 50,
+        }
+    }
+}
+
+impl LintConfig {
+    /// Finds the `velo.toml` closest to `script`, looking in its directory and
+    /// then every directory above it. Returns what's in it, or the path of the
+    /// file together with the problems found in it
+    pub fn find(script: &Path) -> Result<Option<LintConfig>, (PathBuf, Vec<VeloError>)> {
+        let start = match script.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let start = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+
+        for dir in start.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+            if let Ok(contents) = fs::read_to_string(&path) {
+                return match LintConfig::parse(&contents) {
+                    Ok(config) => Ok(Some(config)),
+                    Err(errors) => Err((path, errors)),
+                };
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the few `key = value` lines velo understands. Other sections than
+    /// `[lints]` and comments starting with `#` are skipped
+    pub fn parse(contents: &str) -> Result<LintConfig, Vec<VeloError>> {
+        let mut config = LintConfig::default();
+        let mut errors = Vec::new();
+        let mut in_lints = false;
+
+        for (index, line) in contents.lines().enumerate() {
+            let line_num = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                match line.strip_suffix(']') {
+                    Some(section) => in_lints = section[1..].trim() == "lints",
+                    None => {
+                        let message =
+                            format!("Expected a section header like '[lints]', found '{}'", line);
+                        errors.push(error(line_num, message));
+                        in_lints = false;
+                    }
+                }
+                continue;
+            }
+            if !in_lints {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    errors.push(error(
+                        line_num,
+                        format!("Expected 'key = value', found '{}'", line),
+                    ));
+                    continue;
+                }
+            };
+
+            let result = match (key, Lint::from_name(key)) {
+                ("max_function_lines", _) => match value.parse() {
+                    Ok(max) if max > 0 => {
+                        config.max_function_lines = max;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "'max_function_lines' has to be a positive number, found '{}'",
+                        value
+                    )),
+                },
+                (_, Some(lint)) => match Level::from_name(value.trim_matches('"')) {
+                    Some(level) => {
+                        config.levels.push((lint, level));
+                        Ok(())
+                    }
+                    None => Err(format!(
+                        "Lint '{}' can be set to \"allow\", \"warn\" or \"deny\", found {}",
+                        key, value
+                    )),
+                },
+                _ => Err(format!(
+                    "Unknown lint '{}', expected 'max_function_lines' or one of: {}",
+                    key,
+                    Lint::names()
+                )),
+            };
+            if let Err(message) = result {
+                errors.push(error(line_num, message));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(config),
+            false => Err(errors),
+        }
+    }
+
+    /// Allows and denies lints in `options` as the project file says. Lints
+    /// allowed on the command line stay allowed
+    pub fn configure(&self, options: &mut WarningOptions) {
+        for (lint, level) in &self.levels {
+            match level {
+                Level::Allow => options.allowed.push(*lint),
+                Level::Warn => {}
+                Level::Deny => options.denied.push(*lint),
+            }
+        }
+    }
+}
+
+fn error(line: usize, message: String) -> VeloError {
+    let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
+    VeloError::error(line, &message, ParseError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unclosed_section_header_is_an_error() {
+        let errors = LintConfig::parse("[lints\nshadowing = \"allow\"").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+        assert!(errors[0]
+            .message
+            .contains("Expected a section header like '[lints]'"));
+    }
+
+    /// The lines and messages of the errors found in `contents`
+    fn errors(contents: &str) -> Vec<(usize, String)> {
+        match LintConfig::parse(contents) {
+            Ok(config) => panic!("expected errors in {:?}, found {:?}", contents, config),
+            Err(errors) => errors
+                .into_iter()
+                .map(|error| (error.line, error.message))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn lints_section_sets_levels() {
+        let contents = "
+# project settings
+[package]
+shadowing = \"deny\"
+
+[lints]
+shadowing = \"allow\"   # too noisy
+unreachable_code = \"deny\"
+naming_conventions = \"warn\"
+max_function_lines = 20
+
+[other]
+unknown = 1
+";
+        let config = LintConfig::parse(contents).unwrap();
+        assert_eq!(
+            config.levels,
+            [
+                (Lint::Shadowing, Level::Allow),
+                (Lint::UnreachableCode, Level::Deny),
+                (Lint::NamingConventions, Level::Warn),
+            ]
+        );
+        assert_eq!(config.max_function_lines, 20);
+    }
+
+    #[test]
+    fn every_bad_line_is_reported() {
+        let contents =
+            "[lints]\nshadowing\nshadowing = \"never\"\nunused = \"allow\"\nmax_function_lines = 0";
+        let found = errors(contents);
+        let lines: Vec<usize> = found.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [2, 3, 4, 5]);
+        assert!(found[0]
+            .1
+            .contains("Expected 'key = value', found 'shadowing'"));
+        assert!(found[1]
+            .1
+            .contains("can be set to \"allow\", \"warn\" or \"deny\""));
+        assert!(found[2].1.contains("Unknown lint 'unused'"));
+        assert!(found[3].1.contains("has to be a positive number"));
+    }
+
+    #[test]
+    fn levels_configure_warning_options() {
+        let config =
+            LintConfig::parse("[lints]\nshadowing = \"allow\"\nunused_variables = \"deny\"")
+                .unwrap();
+        let mut options = WarningOptions {
+            allowed: vec![Lint::UnreachableCode],
+            ..Default::default()
+        };
+        config.configure(&mut options);
+        assert_eq!(options.allowed, [Lint::UnreachableCode, Lint::Shadowing]);
+        assert_eq!(options.denied, [Lint::UnusedVariables]);
+    }
+
+    #[test]
+    fn closest_project_file_is_used() {
+        let root = std::env::temp_dir().join(format!("velo-config-{}", std::process::id()));
+        let nested = root.join("src").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(CONFIG_FILE), "[lints]\nshadowing = \"allow\"").unwrap();
+
+        let found = LintConfig::find(&nested.join("main.velo"));
+        fs::write(
+            root.join("src").join(CONFIG_FILE),
+            "[lints]\nshadowing = \"nope\"",
+        )
+        .unwrap();
+        let closer = LintConfig::find(&nested.join("main.velo"));
+        fs::remove_dir_all(&root).ok();
+
+        let config = found.unwrap().unwrap();
+        assert_eq!(config.levels, [(Lint::Shadowing, Level::Allow)]);
+        let (path, errors) = closer.unwrap_err();
+        assert_eq!(path.parent().unwrap().file_name().unwrap(), "src");
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,398 @@
+pub mod config;
+
+use std::collections::HashMap;
+
+use crate::error::{ErrorType::TypeError, Lint, Severity, VeloError, WARNING_INDICATOR};
+use crate::runtime::builtins::builtins;
+use crate::syntax::ast::{Ast, Expression, FunctionBody, Param, Statement};
+use crate::syntax::lexer::TokenType;
+use config::LintConfig;
+
+/// Runs the style lints of `velo lint` over a program that already passed the
+/// checker, returning what they found as warnings
+pub fn lint(nodes: &[Ast], config: &LintConfig) -> Vec<VeloError> {
+    let mut linter = Linter {
+        config,
+        immut: vec![HashMap::new()],
+        line: 0,
+        allowed: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    for node in nodes {
+        match node {
+            Ast::Expression(expr) => linter.expr(expr),
+            Ast::Statement(stmt) => linter.statement(stmt),
+        }
+    }
+    linter.warnings
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    /// The `immut` variables of every scope around the code being linted,
+    /// together with their literal value and the line they're declared on
+    immut: Vec<HashMap<String, (Expression, usize)>>,
+    /// The line of the statement or expression being linted
+    line: usize,
+    /// The lints turned off by the attributes of the statements around the
+    /// code being linted
+    allowed: Vec<Lint>,
+    warnings: Vec<VeloError>,
+}
+
+impl Linter<'_> {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableAssignment {
+                constant,
+                name,
+                value,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                self.expr(value);
+                self.line = *line;
+                self.name("Variable", name, *constant);
+                if *constant && value.is_literal() {
+                    self.immut_declaration(name, value);
+                }
+            }
+            Statement::Reassignment { value, line, .. } | Statement::Return { value, line } => {
+                self.line = *line;
+                self.expr(value);
+            }
+            Statement::IndexAssignment {
+                indices,
+                value,
+                line,
+                ..
+            } => {
+                self.line = *line;
+                for index in indices {
+                    self.expr(index);
+                }
+                self.expr(value);
+            }
+            Statement::Function {
+                name,
+                params,
+                body,
+                line,
+                end_line,
+                ..
+            } => {
+                self.line = *line;
+                self.name("Function", name, false);
+                self.long_function(name, *line, *end_line);
+                self.function(params, body);
+            }
+            Statement::For {
+                name,
+                value_name,
+                iterable,
+                body,
+                line,
+            } => {
+                self.line = *line;
+                self.expr(iterable);
+                self.line = *line;
+                self.name("Variable", name, false);
+                if let Some(value_name) = value_name {
+                    self.name("Variable", value_name, false);
+                }
+                self.block(body);
+            }
+            Statement::Loop { body, .. } => self.block(body),
+            Statement::Export(stmt) => self.statement(stmt),
+            Statement::Attributed { attribute, stmt } => {
+                let outer = self.allowed.len();
+                self.allowed.extend(attribute.allowed());
+                self.statement(stmt);
+                self.allowed.truncate(outer);
+            }
+            Statement::ExprStmt(expr) => self.expr(expr),
+            Statement::ImportPath { .. } | Statement::Break { .. } | Statement::Continue { .. } => {
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Lambda { params, body, .. } => self.function(params, body),
+            Expression::Block(body) => self.block(body),
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch,
+                line,
+            } => {
+                self.line = *line;
+                self.expr(cond);
+                self.default_if(expr);
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.expr(else_branch);
+                }
+            }
+            Expression::Try {
+                body,
+                error_name,
+                handler,
+            } => {
+                self.block(body);
+                if let Some(error_name) = error_name {
+                    self.name("Variable", error_name, false);
+                }
+                self.block(handler);
+            }
+            Expression::CallExpr { params, line, .. } => {
+                self.line = *line;
+                for arg in params {
+                    self.expr(&arg.value);
+                }
+            }
+            Expression::Call {
+                callee,
+                params,
+                line,
+            } => {
+                self.line = *line;
+                self.expr(callee);
+                for arg in params {
+                    self.expr(&arg.value);
+                }
+            }
+            Expression::BinaryOp { lhs, rhs, line, .. } => {
+                self.line = *line;
+                for side in [lhs, rhs] {
+                    if let Ast::Expression(side) = side.as_ref() {
+                        self.expr(side);
+                    }
+                }
+            }
+            Expression::UnaryOp { expr, .. } => self.expr(expr),
+            Expression::Cast { value, .. } => self.expr(value),
+            Expression::Index { target, index, .. } => {
+                self.expr(target);
+                self.expr(index);
+            }
+            Expression::Range {
+                start, end, step, ..
+            } => {
+                self.expr(start);
+                self.expr(end);
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+            }
+            Expression::Array(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+            Expression::Map(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn function(&mut self, params: &[Param], body: &FunctionBody) {
+        for param in params {
+            if let Some(default) = &param.default {
+                self.expr(default);
+            }
+            self.name("Parameter", &param.name, false);
+        }
+        self.block(body);
+    }
+
+    fn block(&mut self, body: &FunctionBody) {
+        self.immut.push(HashMap::new());
+        for stmt in &body.stmts {
+            self.statement(stmt);
+        }
+        if let Some(value) = &body.value {
+            self.expr(value);
+        }
+        self.immut.pop();
+    }
+
+    /// Checks a declared name against the naming conventions, and against the
+    /// names of the built in functions
+    fn name(&mut self, kind: &str, name: &str, constant: bool) {
+        if builtins().iter().any(|function| function.name == name) {
+            let message = format!(
+                "{} '{}' has the same name as a built in function",
+                kind, name
+            );
+            let warning = self
+                .warning(&message, Lint::ShadowedBuiltins)
+                .with_note(
+                    Severity::Note,
+                    format!("calls to '{}' still go to the built in one", name),
+                )
+                .with_note(Severity::Help, "give it another name".to_string());
+            self.warn(warning);
+        }
+
+        // leading underscores mark names that are unused on purpose
+        let trimmed = name.trim_start_matches('_');
+        let snake_case = !trimmed.chars().any(|c| c.is_ascii_uppercase());
+        let screaming = !trimmed.chars().any(|c| c.is_ascii_lowercase());
+        if snake_case || (constant && screaming) {
+            return;
+        }
+
+        let message = format!("{} '{}' should have a snake_case name", kind, name);
+        let warning = self.warning(&message, Lint::NamingConventions).with_note(
+            Severity::Help,
+            format!("rename it to '{}'", to_snake_case(name)),
+        );
+        self.warn(warning);
+    }
+
+    /// Warns about `immut x := value` when an `immut x` with the same value is
+    /// already visible from an enclosing scope
+    fn immut_declaration(&mut self, name: &str, value: &Expression) {
+        let outer = self.immut[..self.immut.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .filter(|(outer, _)| outer == value)
+            .map(|(_, line)| *line);
+
+        if let Some(line) = outer {
+            let message = format!(
+                "'{}' is already declared immut with the same value, on line {}",
+                name, line
+            );
+            let warning = self.warning(&message, Lint::RedundantImmut).with_note(
+                Severity::Help,
+                format!(
+                    "remove this declaration, the one on line {} can be used here",
+                    line
+                ),
+            );
+            self.warn(warning);
+        }
+
+        if let Some(scope) = self.immut.last_mut() {
+            scope.insert(name.to_string(), (value.clone(), self.line));
+        }
+    }
+
+    /// Warns about comparing a value that can never be `null` to `null`
+    /// Warns about `if x == null { default } else { x }`, which is `x ?? default`
+    fn default_if(&mut self, expr: &Expression) {
+        let (cond, then_branch, else_branch) = match expr {
+            Expression::If {
+                cond,
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => (cond, then_branch, else_branch),
+            _ => return,
+        };
+        let (name, op) = match cond.as_ref() {
+            Expression::BinaryOp { lhs, op, rhs, .. } => match (lhs.as_ref(), rhs.as_ref()) {
+                (
+                    Ast::Expression(Expression::Identifier(name)),
+                    Ast::Expression(Expression::Null),
+                )
+                | (
+                    Ast::Expression(Expression::Null),
+                    Ast::Expression(Expression::Identifier(name)),
+                ) => (name, *op),
+                _ => return,
+            },
+            _ => return,
+        };
+        let else_value = match else_branch.as_ref() {
+            Expression::Block(body) => only_value(body),
+            _ => None,
+        };
+        let (present, default) = match op {
+            TokenType::EqEq => (else_value, only_value(then_branch)),
+            TokenType::Ne => (only_value(then_branch), else_value),
+            _ => return,
+        };
+
+        match (present, default) {
+            (Some(Expression::Identifier(present)), Some(_)) if present == name => {
+                let message = format!("This 'if' can be written as '{} ?? <default>'", name);
+                let warning = self.warning(&message, Lint::NullComparison).with_note(
+                    Severity::Help,
+                    "'??' gives the value on its left unless it's null".to_string(),
+                );
+                self.warn(warning);
+            }
+            _ => {}
+        }
+    }
+
+    fn long_function(&mut self, name: &str, line: usize, end_line: usize) {
+        let length = end_line.saturating_sub(line) + 1;
+        let max = self.config.max_function_lines;
+        if length <= max {
+            return;
+        }
+
+        let message = format!(
+            "Function '{}' is {} lines long, more than the {} allowed",
+            name, length, max
+        );
+        let warning = self.warning(&message, Lint::LongFunctions).with_note(
+            Severity::Help,
+            "split it into smaller functions, or raise 'max_function_lines' in velo.toml"
+                .to_string(),
+        );
+        self.warn(warning);
+    }
+
+    /// Reports a warning, unless an `@allow` around it turns its lint off
+    fn warn(&mut self, warning: VeloError) {
+        if !warning
+            .lint
+            .is_some_and(|lint| self.allowed.contains(&lint))
+        {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn warning(&self, message: &str, lint: Lint) -> VeloError {
+        let message = format!("{} \x1b[1m{}\x1b[0m", WARNING_INDICATOR, message);
+        VeloError::warning(self.line, &message, TypeError, lint)
+    }
+}
+
+/// The value of a block that's nothing but a value, i.e. `{ x }`
+fn only_value(body: &FunctionBody) -> Option<&Expression> {
+    match body.stmts.is_empty() {
+        true => body.value.as_deref(),
+        false => None,
+    }
+}
+
+/// `camelCase` and `PascalCase` names in snake_case, i.e. `my_name`
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (index, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let after_word = index > 0 && chars[index - 1] != '_';
+            let before_lower = chars.get(index + 1).is_some_and(char::is_ascii_lowercase);
+            let after_lower = index > 0 && !chars[index - 1].is_ascii_uppercase();
+            if after_word && (after_lower || before_lower) {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
@@ -1,37 +1,105 @@
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
-use std::{env, fs};
+use std::{env, fs, thread};
 
-use runtime::environment::Environment;
+use checker::{check, Checked};
+use error::{report_errors, Lint, VeloError, WarningOptions};
+use lint::config::LintConfig;
 use runtime::interpreter::evaluate;
+use syntax::ast::Ast;
 use syntax::lexer::Lexer;
 use syntax::parse::Parser;
 
+mod checker;
 mod error;
+mod lint;
 mod runtime;
 mod syntax;
 
+const USAGE: &str =
+    "Usage: velo [check [--types] | lint] [--deny-warnings] [--allow <lint>]... [file]";
+
+/// The stack the interpreter runs on, big enough for calls nested as deeply as
+/// the runtime allows, even in debug builds
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() == 1 {
-        repl()
-    } else if args.len() == 2 {
-        let path = &args[1];
-        if let Ok(contents) = fs::read_to_string(path) {
-            parse_file(contents)
-        } else {
-            eprintln!("Error reading file: {}", path);
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter");
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
+    let mut options = WarningOptions::default();
+    // `velo check --types <file>` also lists the type of every binding
+    let mut show_types = false;
+    let mut rest = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--deny-warnings" => options.deny = true,
+            "--allow" => match args.next().as_deref().and_then(Lint::from_name) {
+                Some(lint) => options.allowed.push(lint),
+                None => {
+                    eprintln!(
+                        "Expected the name of a lint after '--allow', one of: {}",
+                        Lint::names()
+                    );
+                    process::exit(1);
+                }
+            },
+            "--types" => show_types = true,
+            _ => rest.push(arg),
+        }
+    }
+
+    let (command, path) = match rest.as_slice() {
+        [] => return repl(options),
+        [command, path] if command == "check" || command == "lint" => (command.as_str(), path),
+        [path] => ("run", path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return eprintln!("Error reading file: {}", path),
+    };
+    let path = Path::new(path);
+
+    // lints set to "allow" in velo.toml stay allowed whatever the command line
+    // says, so the project file is applied before it
+    let config = match LintConfig::find(path) {
+        Ok(Some(config)) => config,
+        Ok(None) => LintConfig::default(),
+        Err((config_path, errors)) => {
+            report_errors(&errors, &config_path.display().to_string());
+            process::exit(1);
         }
+    };
+    config.configure(&mut options);
+
+    match command {
+        "check" => check_file(contents, path, &options, show_types),
+        "lint" => lint_file(contents, path, &options, &config),
+        _ => parse_file(contents, path, &options),
     }
 }
 
-fn repl() {
+fn repl(mut options: WarningOptions) {
     println!("Velo REPL [beta]\nUse `quit` to exit safely\n");
     println!("NOTES TO SELF:");
-    println!(
-        "Implement function body parsing\nRefactors\nWarning Emission\nTuple Types in functions"
-    );
+    println!("Refactors\nTuple Types in functions");
+
+    // every line runs on its own, so nothing it declares is used later
+    options.allowed.push(Lint::UnusedVariables);
 
     loop {
         print!("> ");
@@ -46,24 +114,95 @@ fn repl() {
             process::exit(0);
         }
 
-        let mut lexer = Lexer::new(&input);
-        let tokens = lexer.tokenize();
-        let tokens = tokens.tokens;
-
-        let mut parser = Parser::new(tokens);
-        let ast = parser.parse();
-
-        evaluate(parser.nodes);
+        match parse_and_check(input, None, &options, None) {
+            Ok((ast, checked)) => {
+                report_errors(&checked.warnings, "repl");
+                report_errors(&evaluate(ast, None), "repl");
+            }
+            Err(errors) => report_errors(&errors, "repl"),
+        }
     }
 }
 
-fn parse_file(contents: String) {
-    let mut lexer = Lexer::new(&contents);
+/// Parses and checks `source`, the contents of `file`, keeping only the
+/// warnings `options` allow. The style lints only run when `lints` configures
+/// them
+fn parse_and_check(
+    source: &str,
+    file: Option<&Path>,
+    options: &WarningOptions,
+    lints: Option<&LintConfig>,
+) -> Result<(Vec<Ast>, Checked), Vec<VeloError>> {
+    let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize();
     let tokens = tokens.tokens;
 
-    let mut parser = Parser::new(tokens);
-    let ast = parser.parse();
+    let ast = Parser::new(&tokens).parse()?;
+    let mut checked = check(&ast, file)?;
+    if let Some(config) = lints {
+        checked.warnings.extend(lint::lint(&ast, config));
+        checked.warnings.append(&mut checked.lints);
+        checked.warnings.sort_by_key(|warning| warning.line);
+    }
+
+    checked.warnings = options.apply(checked.warnings)?;
+    Ok((ast, checked))
+}
+
+fn parse_file(contents: String, path: &Path, options: &WarningOptions) {
+    let filename = path.display().to_string();
+    match parse_and_check(&contents, Some(path), options, None) {
+        Ok((ast, checked)) => {
+            report_errors(&checked.warnings, &filename);
+            let errors = evaluate(ast, Some(path));
+            if !errors.is_empty() {
+                report_errors(&errors, &filename);
+                process::exit(1);
+            }
+        }
+        Err(errors) => {
+            report_errors(&errors, &filename);
+            process::exit(1);
+        }
+    }
+}
 
-    evaluate(parser.nodes);
+/// Parses and type checks a file without running it
+fn check_file(contents: String, path: &Path, options: &WarningOptions, show_types: bool) {
+    let filename = path.display().to_string();
+    match parse_and_check(&contents, Some(path), options, None) {
+        Ok((_, checked)) => {
+            report_errors(&checked.warnings, &filename);
+            if show_types {
+                for binding in checked.bindings {
+                    let ty = binding.type_name();
+                    println!("{}:{}: {} {}", filename, binding.line, binding.name, ty);
+                }
+            }
+            println!("No errors found in {}", filename)
+        }
+        Err(errors) => {
+            report_errors(&errors, &filename);
+            process::exit(1);
+        }
+    }
+}
+
+/// Checks a file and runs the style lints over it, without running it
+fn lint_file(contents: String, path: &Path, options: &WarningOptions, config: &LintConfig) {
+    let filename = path.display().to_string();
+    match parse_and_check(&contents, Some(path), options, Some(config)) {
+        Ok((_, checked)) => {
+            report_errors(&checked.warnings, &filename);
+            match checked.warnings.len() {
+                0 => println!("No problems found in {}", filename),
+                1 => println!("1 warning in {}", filename),
+                count => println!("{} warnings in {}", count, filename),
+            }
+        }
+        Err(errors) => {
+            report_errors(&errors, &filename);
+            process::exit(1);
+        }
+    }
 }
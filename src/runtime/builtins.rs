@@ -0,0 +1,105 @@
+use super::environment::LibFunction;
+use super::value::Value;
+use crate::syntax::ast::{Expression, Param};
+use crate::syntax::lexer::Type;
+
+/// The functions every program can call without importing anything
+pub fn builtins() -> Vec<LibFunction> {
+    // integer arithmetic that wraps around or stops at the type's bounds
+    // instead of raising an error on overflow
+    let integer = |name| param(name, Type::Param("T".to_string()));
+    let overflowing = ["wrapping", "saturating"]
+        .into_iter()
+        .flat_map(|kind| ["add", "sub", "mul"].map(|op| format!("{}_{}", kind, op)))
+        .map(|name| {
+            lib(
+                &name,
+                vec![integer("a"), integer("b")],
+                Type::Param("T".to_string()),
+            )
+        });
+
+    let mut functions = vec![
+        lib("print", vec![variadic("values", Type::Any)], Type::Void),
+        lib("println", vec![variadic("values", Type::Any)], Type::Void),
+        lib(
+            "input",
+            vec![optional("prompt", Type::String, string(""))],
+            Type::String,
+        ),
+        lib(
+            "to_array",
+            vec![param("items", Type::Any)],
+            Type::Array(Box::new(Type::Any)),
+        ),
+        lib(
+            "panic",
+            vec![optional("message", Type::Any, string("Program panicked"))],
+            Type::Void,
+        ),
+        lib("read_file", vec![param("path", Type::String)], Type::String),
+    ];
+    functions.extend(overflowing);
+    functions
+}
+
+type LibraryMembers = (Vec<(&'static str, Value)>, Vec<LibFunction>);
+
+/// The values and functions of a library module like `std::math`, or `None`
+/// if there's no such library
+pub fn library(path: &str) -> Option<LibraryMembers> {
+    let number = |name| param(name, Type::Float);
+    match path {
+        "std::math" => Some((
+            vec![
+                ("pi", Value::Float(std::f32::consts::PI)),
+                ("e", Value::Float(std::f32::consts::E)),
+            ],
+            vec![
+                lib("sqrt", vec![number("x")], Type::Float),
+                lib("abs", vec![number("x")], Type::Float),
+                lib("floor", vec![number("x")], Type::Float),
+                lib("ceil", vec![number("x")], Type::Float),
+                lib("pow", vec![number("base"), number("exponent")], Type::Float),
+                lib("min", vec![number("a"), number("b")], Type::Float),
+                lib("max", vec![number("a"), number("b")], Type::Float),
+            ],
+        )),
+        _ => None,
+    }
+}
+
+fn lib(name: &str, params: Vec<Param>, ret: Type) -> LibFunction {
+    LibFunction {
+        name: name.to_string(),
+        params,
+        ret,
+    }
+}
+
+fn param(name: &str, ty: Type) -> Param {
+    Param {
+        name: name.to_string(),
+        ty,
+        default: None,
+        variadic: false,
+    }
+}
+
+fn optional(name: &str, ty: Type, default: Expression) -> Param {
+    Param {
+        default: Some(default),
+        ..param(name, ty)
+    }
+}
+
+fn variadic(name: &str, ty: Type) -> Param {
+    Param {
+        variadic: true,
+        ..param(name, ty)
+    }
+}
+
+fn string(value: &str) -> Expression {
+    Expression::StringLiteral(value.to_string())
+}
@@ -1,75 +1,367 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
 
+use super::builtins::builtins;
+use super::eval::number;
+use super::module::Module;
+use super::value::Value;
 use crate::error::{ErrorType::RuntimeError, VeloError, ERROR_INDICATOR};
-use crate::syntax::ast::{Expression, Statement};
+use crate::syntax::ast::Param;
+use crate::syntax::lexer::Type;
+
+/// How deeply function calls can nest before the program is stopped, instead
+/// of overflowing the interpreter's stack
+const MAX_CALL_DEPTH: usize = 1000;
+
+/// A shared handle to an environment, so closures can keep the scope they were
+/// defined in alive and see later changes to it
+pub type Scope = Rc<RefCell<Environment>>;
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub errors: Vec<VeloError>,
-    pub parent: Option<Box<Environment>>,
-    pub variables: HashMap<String, Expression>,
-    pub constants: Vec<Expression>,
-    pub functions: Vec<Statement>,
+    pub parent: Option<Scope>,
+    pub variables: HashMap<String, Value>,
+    pub constants: HashMap<String, Value>,
+    /// The types variables were declared with, which the values they're given
+    /// later are converted to
+    pub types: HashMap<String, Type>,
     pub lib_functions: Vec<LibFunction>,
+    pub modules: HashMap<String, Module>,
+    pub exports: Vec<String>,
+    /// The value of a `return` hit in this scope, on its way out to the
+    /// function it belongs to
+    pub returning: Option<Value>,
+    /// A `break` or `continue` hit in this scope, on its way out to the loop
+    /// it belongs to
+    pub jumping: Option<Jump>,
+    /// The message of an error on its way out to the nearest `try`, together
+    /// with the line it was raised on. Only set in the outermost scope
+    pub raised: Option<(String, usize)>,
+    /// The line of the code being run, only kept in the outermost scope
+    pub line: usize,
+    /// How many function calls deep the code being run is, only kept in the
+    /// outermost scope
+    pub depth: usize,
 }
 
+/// How a `break` or `continue` leaves the rest of a loop's body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    Break,
+    Continue,
+}
+
+/// A function provided by the interpreter, with the signature calls to it are
+/// checked against
 #[derive(Debug, Clone, PartialEq)]
 pub struct LibFunction {
     pub name: String,
-    pub param_len: Option<usize>,
+    pub params: Vec<Param>,
+    pub ret: Type,
+}
+
+/// The environment captured by a closure. Two captures are only equal when
+/// they point at the same scope
+#[derive(Clone)]
+pub struct Captured(pub Scope);
+
+impl fmt::Debug for Captured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // printing the scope itself would recurse forever when a closure is
+        // stored in the scope it captured
+        write!(f, "Captured {{ .. }}")
+    }
+}
+
+impl PartialEq for Captured {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 impl Environment {
     pub fn init() -> Self {
-        let funcs = vec![("print", Some(1)), ("println", Some(1)), ("input", Some(2))];
-        let mut lib_functions = Vec::new();
-        for f in funcs {
-            lib_functions.push(Self::mk_lib(f.0, f.1));
+        Environment {
+            errors: Vec::new(),
+            parent: None,
+            variables: HashMap::new(),
+            constants: HashMap::new(),
+            types: HashMap::new(),
+            lib_functions: builtins(),
+            modules: HashMap::new(),
+            exports: Vec::new(),
+            returning: None,
+            jumping: None,
+            raised: None,
+            line: 0,
+            depth: 0,
         }
+    }
 
+    /// Creates a new scope nested inside `parent`, e.g. for a function call
+    pub fn child(parent: Scope) -> Self {
         Environment {
             errors: Vec::new(),
-            parent: None,
+            parent: Some(parent),
             variables: HashMap::new(),
-            constants: Vec::new(),
-            functions: Vec::new(),
-            lib_functions,
+            constants: HashMap::new(),
+            types: HashMap::new(),
+            lib_functions: Vec::new(),
+            modules: HashMap::new(),
+            exports: Vec::new(),
+            returning: None,
+            jumping: None,
+            raised: None,
+            line: 0,
+            depth: 0,
         }
     }
 
-    pub fn mk_lib(name: &str, len: Option<usize>) -> LibFunction {
-        LibFunction {
-            name: name.to_string(),
-            param_len: len,
+    pub fn declare_variable(&mut self, name: String, value: Value, constant: bool) -> Value {
+        if self.variables.contains_key(&name) || self.constants.contains_key(&name) {
+            let message = format!(
+                "Variable with name '{}' already exists, did you mean to use `:=` instead of `=`?",
+                &name
+            );
+            self.throw_error(message)
+        } else if constant {
+            self.constants.insert(name, value);
+        } else {
+            self.variables.insert(name, value);
         }
+
+        Value::Null
     }
 
-    pub fn declare_variable(
-        &mut self,
-        name: String,
-        value: Expression,
-        constant: bool,
-    ) -> Expression {
-        if constant {
-            self.constants.push(value)
-            // yk i would make constants a hash set
-            // but that doesnt work with f32 for some weird reason
-            // i love rust :)
+    /// Notes the type a variable declared in this scope keeps, e.g. `float` for
+    /// `x: float := 1`
+    pub fn declare_type(&mut self, name: &str, ty: Type) {
+        self.types.insert(name.to_string(), ty);
+    }
+
+    /// Updates an existing variable in the nearest scope that declares it,
+    /// storing numbers as the type the variable was declared with
+    pub fn assign_variable(&mut self, name: &str, value: Value) -> Result<(), String> {
+        if let Some(variable) = self.variables.get_mut(name) {
+            *variable = match self.types.get(name) {
+                Some(ty) if !value.matches_type(ty) => {
+                    return Err(format!(
+                        "Mismatched types: variable '{}' is declared as '{}' but its new value is of type '{}'",
+                        name,
+                        ty,
+                        value.type_name()
+                    ))
+                }
+                Some(ty) => number::convert(value, ty),
+                None => value,
+            };
+            Ok(())
+        } else if self.constants.contains_key(name) {
+            Err(format!(
+                "Cannot assign twice to immutable variable '{}'",
+                name
+            ))
+        } else if let Some(parent) = &self.parent {
+            parent.borrow_mut().assign_variable(name, value)
+        } else {
+            Err(format!("Cannot find value '{}' in scope", name))
+        }
+    }
+
+    /// Looks up a variable or constant through the enclosing scopes, following
+    /// `module::name` paths into imported modules
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        if let Some((module, member)) = name.split_once("::") {
+            return match self.modules.get(module) {
+                Some(module) => module.variables.get(member).cloned(),
+                None => self.parent.as_ref()?.borrow().get_variable(name),
+            };
+        }
+
+        match self
+            .variables
+            .get(name)
+            .or_else(|| self.constants.get(name))
+        {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get_variable(name),
+        }
+    }
+
+    pub fn get_module(&self, name: &str) -> Option<Module> {
+        match self.modules.get(name) {
+            Some(module) => Some(module.clone()),
+            None => self.parent.as_ref()?.borrow().get_module(name),
+        }
+    }
+
+    /// Looks up a built-in function, which are only declared in the root scope
+    pub fn get_lib_function(&self, name: &str) -> Option<LibFunction> {
+        match &self.parent {
+            Some(parent) => parent.borrow().get_lib_function(name),
+            None => self
+                .lib_functions
+                .iter()
+                .find(|lib| lib.name == name)
+                .cloned(),
+        }
+    }
+
+    pub fn declare_module(&mut self, module: Module) {
+        if self.modules.contains_key(&module.name) {
+            let message = format!("A module named '{}' has already been imported", module.name);
+            self.throw_error(message)
         } else {
-            if self.variables.contains_key(&name) {
-                let message = format!("Variable with name '{}' already exists, did you mean to use `:=` instead of `=`?", &name);
-                self.throw_error(message)
-            } else {
-                self.variables.insert(name, value);
+            self.modules.insert(module.name.clone(), module);
+        }
+    }
+
+    /// Notes the line of the code that's about to run, which errors raised by
+    /// it are reported on
+    pub fn set_line(&mut self, line: usize) {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().set_line(line),
+            None => self.line = line,
+        }
+    }
+
+    /// Raises a runtime error on the line being run, which stops evaluation
+    /// until it's caught by a `try` or reaches the top of the program
+    pub fn throw_error(&mut self, message: String) {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().throw_error(message),
+            None => self.raise(message, self.line),
+        }
+    }
+
+    /// Raises a runtime error that happened on `line`
+    pub fn raise(&mut self, message: String, line: usize) {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().raise(message, line),
+            // only the first error is kept, anything raised while unwinding
+            // from it is a consequence of it
+            None => {
+                if self.raised.is_none() {
+                    self.raised = Some((message, line));
+                }
+            }
+        }
+    }
+
+    /// Notes that a function call is starting, raising an error instead when
+    /// calls are already nested `MAX_CALL_DEPTH` deep. Returns whether the
+    /// call can go ahead
+    pub fn enter_call(&mut self) -> bool {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().enter_call(),
+            None if self.depth >= MAX_CALL_DEPTH => {
+                let message = format!(
+                    "Function calls are nested more than {} deep, is there a recursion without an end?",
+                    MAX_CALL_DEPTH
+                );
+                self.throw_error(message);
+                false
             }
+            None => {
+                self.depth += 1;
+                true
+            }
+        }
+    }
+
+    /// Notes that a function call started with `enter_call` has finished
+    pub fn leave_call(&mut self) {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().leave_call(),
+            None => self.depth = self.depth.saturating_sub(1),
+        }
+    }
+
+    pub fn is_raised(&self) -> bool {
+        match &self.parent {
+            Some(parent) => parent.borrow().is_raised(),
+            None => self.raised.is_some(),
+        }
+    }
+
+    /// Stops the error being raised, returning its message and line
+    pub fn take_raised(&mut self) -> Option<(String, usize)> {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().take_raised(),
+            None => self.raised.take(),
+        }
+    }
+
+    /// Records the error that reached the top of the program without being
+    /// caught
+    pub fn report_raised(&mut self) {
+        if let Some((message, line)) = self.take_raised() {
+            let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
+            self.push_error(VeloError::error(line, &message, RuntimeError));
+        }
+    }
+
+    // Errors are always collected in the outermost scope, which outlives the
+    // scopes created for function calls
+    fn push_error(&mut self, error: VeloError) {
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().push_error(error),
+            None => self.errors.push(error),
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_values_are_stored_as_the_declared_type() {
+        let mut env = Environment::init();
+        env.declare_variable("x".to_string(), Value::Float(1.0), false);
+        env.declare_type("x", Type::Float);
+        env.assign_variable("x", Value::Int(3)).unwrap();
+        assert!(matches!(env.get_variable("x"), Some(Value::Float(n)) if n == 3.0));
+    }
+
+    #[test]
+    fn new_values_have_to_fit_the_declared_type() {
+        let mut env = Environment::init();
+        env.declare_variable("s".to_string(), Value::Short(1), false);
+        env.declare_type("s", Type::Short);
+        let error = env.assign_variable("s", Value::Int(40000)).unwrap_err();
+        assert!(error.contains("declared as 'short'"), "{}", error);
+        assert!(matches!(env.get_variable("s"), Some(Value::Short(1))));
+    }
+
+    #[test]
+    fn variables_of_outer_scopes_keep_their_type() {
+        let root = Rc::new(RefCell::new(Environment::init()));
+        root.borrow_mut()
+            .declare_variable("xs".to_string(), Value::Array(Vec::new()), false);
+        root.borrow_mut()
+            .declare_type("xs", Type::Array(Box::new(Type::Float)));
 
-        Expression::Null
+        let mut child = Environment::child(root.clone());
+        child
+            .assign_variable("xs", Value::Array(vec![Value::Int(2)]))
+            .unwrap();
+        let xs = root.borrow().get_variable("xs");
+        assert!(matches!(
+            xs,
+            Some(Value::Array(items)) if matches!(items.as_slice(), [Value::Float(_)])
+        ));
     }
 
-    fn throw_error(&mut self, message: String) {
-        let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
-        self.errors
-            .push(VeloError::error(0, &message, RuntimeError));
+    #[test]
+    fn variables_without_a_type_take_any_value() {
+        let mut env = Environment::init();
+        env.declare_variable("x".to_string(), Value::Int(1), false);
+        env.assign_variable("x", Value::String("a".to_string()))
+            .unwrap();
+        assert_eq!(env.get_variable("x"), Some(Value::String("a".to_string())));
     }
 }
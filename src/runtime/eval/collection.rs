@@ -0,0 +1,258 @@
+use super::number;
+use crate::runtime::value::Value;
+
+/// An evaluated range, i.e. `0..10 step 2`
+#[derive(Debug, Clone, Copy)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub inclusive: bool,
+    pub step: f64,
+    /// Whether every bound is an integer, which makes the numbers in the
+    /// range integers too
+    pub integer: bool,
+}
+
+impl Range {
+    /// Reads a range value whose bounds have already been evaluated
+    pub fn from_value(value: &Value) -> Option<Range> {
+        match value {
+            Value::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                let bounds = [Some(start.as_ref()), Some(end.as_ref()), step.as_deref()];
+                let integer = bounds
+                    .iter()
+                    .flatten()
+                    .all(|bound| number::integer(bound).is_some());
+                let step = match step.as_deref() {
+                    Some(step) => number::float(step)?,
+                    None => 1.0,
+                };
+                Some(Range {
+                    start: number::float(start)?,
+                    end: number::float(end)?,
+                    inclusive: *inclusive,
+                    step,
+                    integer,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Every number in the range, counting down when the step is negative
+    pub fn iter(self) -> impl Iterator<Item = f64> {
+        // computing each value from its index keeps float steps from drifting
+        (0..)
+            .map(move |i| self.start + i as f64 * self.step)
+            .take_while(move |n| match (self.step > 0.0, self.inclusive) {
+                (true, true) => *n <= self.end,
+                (true, false) => *n < self.end,
+                (false, true) => *n >= self.end,
+                (false, false) => *n > self.end,
+            })
+    }
+
+    /// Every number in the range as a value, i.e. ints for integer ranges
+    pub fn values(self) -> impl Iterator<Item = Value> {
+        self.iter().map(move |n| match self.integer {
+            true => number::whole(n as i64),
+            false => Value::Float(n as f32),
+        })
+    }
+
+    pub fn contains(&self, n: f64) -> bool {
+        let (low, high) = match self.step > 0.0 {
+            true => (self.start, self.end),
+            false => (self.end, self.start),
+        };
+        let in_bounds = match self.inclusive {
+            true => n >= low && n <= high,
+            false if self.step > 0.0 => n >= low && n < high,
+            false => n > low && n <= high,
+        };
+        in_bounds && ((n - self.start) / self.step).fract() == 0.0
+    }
+}
+
+/// The items a `for` loop or a spread goes over, which for maps are their keys
+pub fn iterate(value: Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::Map(entries) => Ok(entries.into_iter().map(|(key, _)| key).collect()),
+        Value::String(string) => Ok(string
+            .chars()
+            .map(|c| Value::String(c.to_string()))
+            .collect()),
+        value => match Range::from_value(&value) {
+            Some(range) => Ok(range.values().collect()),
+            None => Err(format!(
+                "Cannot iterate over a value of type '{}'",
+                value.type_name()
+            )),
+        },
+    }
+}
+
+/// The pairs `for key, value in collection` goes over: the entries of maps,
+/// and every item together with its index for everything else
+pub fn iterate_pairs(value: Value) -> Result<Vec<(Value, Value)>, String> {
+    match value {
+        Value::Map(entries) => Ok(entries),
+        value => Ok(iterate(value)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| (number::whole(index as i64), item))
+            .collect()),
+    }
+}
+
+/// Builds a map from evaluated entries, where a repeated key keeps its first
+/// position but takes the last value
+pub fn make_map(entries: Vec<(Value, Value)>) -> Result<Value, String> {
+    let mut map = Vec::new();
+    for (key, value) in entries {
+        insert(&mut map, key, value)?;
+    }
+    Ok(Value::Map(map))
+}
+
+fn insert(map: &mut Vec<(Value, Value)>, key: Value, value: Value) -> Result<(), String> {
+    if !matches!(
+        key,
+        Value::String(_)
+            | Value::Short(_)
+            | Value::Int(_)
+            | Value::Large(_)
+            | Value::Float(_)
+            | Value::Bool(_)
+    ) {
+        return Err(format!(
+            "Map keys must be strings, numbers or bools, found a value of type '{}'",
+            key.type_name()
+        ));
+    }
+
+    match map.iter_mut().find(|(existing, _)| existing == &key) {
+        Some((_, existing)) => *existing = value,
+        None => map.push((key, value)),
+    }
+    Ok(())
+}
+
+/// Whether `item` is part of `collection`, for `item in collection`
+pub fn contains(collection: &Value, item: &Value) -> Result<bool, String> {
+    match (collection, item) {
+        (Value::Array(items), item) => Ok(items.iter().any(|i| i == item)),
+        (Value::Map(entries), key) => Ok(entries.iter().any(|(k, _)| k == key)),
+        (Value::String(string), Value::String(part)) => Ok(string.contains(part.as_str())),
+        (Value::Range { .. }, item) if number::float(item).is_some() => {
+            Ok(Range::from_value(collection)
+                .zip(number::float(item))
+                .is_some_and(|(range, n)| range.contains(n)))
+        }
+        _ => Err(format!(
+            "Cannot check whether a value of type '{}' is in a value of type '{}'",
+            item.type_name(),
+            collection.type_name()
+        )),
+    }
+}
+
+/// `target[index]`, where indexing with a range takes a slice
+pub fn index(target: &Value, index: &Value) -> Result<Value, String> {
+    if let Value::Map(entries) = target {
+        return match entries.iter().find(|(key, _)| key == index) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(format!("Map has no key '{}'", index)),
+        };
+    }
+
+    let len = match target {
+        Value::Array(items) => items.len(),
+        Value::String(string) => string.chars().count(),
+        _ => {
+            return Err(format!(
+                "Cannot index into a value of type '{}'",
+                target.type_name()
+            ))
+        }
+    };
+
+    let positions: Vec<f64> = match (number::integer(index), Range::from_value(index)) {
+        (Some(n), _) => vec![n as f64],
+        (None, Some(range)) if range.integer => range.iter().collect(),
+        _ => {
+            return Err(format!(
+                "Cannot index with a value of type '{}'",
+                index.type_name()
+            ))
+        }
+    };
+
+    let mut indices = Vec::new();
+    for n in positions {
+        if n < 0.0 || n as usize >= len {
+            return Err(format!("Index {} is out of bounds for length {}", n, len));
+        }
+        indices.push(n as usize);
+    }
+
+    let slice = matches!(index, Value::Range { .. });
+    match target {
+        Value::Array(items) => {
+            let mut picked: Vec<Value> = indices.iter().map(|i| items[*i].clone()).collect();
+            match slice {
+                true => Ok(Value::Array(picked)),
+                false => Ok(picked.remove(0)),
+            }
+        }
+        Value::String(string) => {
+            let chars: Vec<char> = string.chars().collect();
+            Ok(Value::String(indices.iter().map(|i| chars[*i]).collect()))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// `target[indices[0]][indices[1]]... = value`, where a missing map key is
+/// inserted
+pub fn set_index(target: &mut Value, indices: &[Value], value: Value) -> Result<(), String> {
+    let (index, rest) = match indices.split_first() {
+        Some(split) => split,
+        None => {
+            *target = value;
+            return Ok(());
+        }
+    };
+
+    match target {
+        Value::Map(entries) => match entries.iter_mut().find(|(key, _)| key == index) {
+            Some((_, existing)) => set_index(existing, rest, value),
+            None if rest.is_empty() => insert(entries, index.clone(), value),
+            None => Err(format!("Map has no key '{}'", index)),
+        },
+        Value::Array(items) => match number::integer(index) {
+            Some(n) if n >= 0 && (n as usize) < items.len() => {
+                set_index(&mut items[n as usize], rest, value)
+            }
+            Some(n) => Err(format!(
+                "Index {} is out of bounds for length {}",
+                n,
+                items.len()
+            )),
+            None => Err(format!(
+                "Cannot index an array with a value of type '{}'",
+                index.type_name()
+            )),
+        },
+        _ => Err(format!(
+            "Cannot assign to an index of a value of type '{}'",
+            target.type_name()
+        )),
+    }
+}
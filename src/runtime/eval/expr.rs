@@ -1,96 +1,821 @@
-use super::super::environment::Environment;
-use crate::syntax::ast::{Expression, Statement};
-
-use std::io;
-
-pub fn eval_call_expr(call_expr: &Expression, env: &mut Environment, var: Option<&Statement>) {
-    let mut nm = String::new();
-    let mut pm: Vec<Expression> = Vec::new();
-    match call_expr {
-        Expression::CallExpr { name, params } => {
-            nm = name.to_string();
-            pm = params.to_vec();
-        }
-        _ => unreachable!(),
-    }
-    let mut is_lib = false;
-
-    let name = nm.clone();
-    let params = pm.clone();
-
-    for lib in env.lib_functions.iter() {
-        if name == lib.name {
-            is_lib = true
-        } else {
-            continue;
-        }
-    }
-
-    if is_lib {
-        match name.as_str() {
-            "print" | "println" => {
-                let mut line = false;
-                if name.as_str() == "println" {
-                    line = true
-                }
-                match &params[0] {
-                    Expression::Identifier(ident) => {
-                        if let Some(expr) = env.variables.get(ident) {
-                            match expr {
-                                Expression::StringLiteral(str) => {
-                                    if line {
-                                        println!("{}", str)
-                                    } else {
-                                        print!("{}", str)
-                                    }
-                                }
-                                _ => {
-                                    println!("{:#?}", expr);
-                                    todo!()
-                                }
-                            }
-                        } else {
-                            std::process::exit(1)
-                        }
-                    }
-                    Expression::StringLiteral(ident) => {
-                        if line {
-                            println!("{}", ident)
-                        } else {
-                            print!("{}", ident)
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use super::super::environment::{Captured, Environment, LibFunction, Scope};
+use super::collection;
+use super::number::{self, Overflow};
+use super::stmt::eval_body;
+use crate::runtime::value::Value;
+use crate::syntax::ast::{Argument, Ast, Expression, FunctionBody, Param};
+use crate::syntax::lexer::{TokenType, Type};
+
+/// An evaluated argument of a call, named when it's passed as `name: value`
+struct Arg {
+    name: Option<String>,
+    value: Value,
+}
+
+pub fn eval_expr(expr: &Expression, env: &Scope) -> Value {
+    match expr {
+        Expression::Short(n) => Value::Short(*n),
+        Expression::Int(n) => Value::Int(*n),
+        Expression::Large(n) => Value::Large(*n),
+        Expression::Float(n) => Value::Float(*n),
+        Expression::Bool(b) => Value::Bool(*b),
+        Expression::StringLiteral(string) => Value::String(string.clone()),
+        Expression::Null => Value::Null,
+        Expression::Identifier(name) => {
+            let value = env.borrow().get_variable(name);
+            match value {
+                Some(value) => value,
+                // built ins are only values when no variable shadows them
+                None if find_lib_function(name, env).is_some() => Value::Builtin(name.clone()),
+                None => {
+                    let message = format!("Cannot find value '{}' in scope", name);
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        Expression::BinaryOp {
+            lhs,
+            op: TokenType::QuestionQuestion,
+            rhs,
+            ..
+        } => match eval_ast(lhs, env) {
+            // the default is only evaluated when it's needed
+            Value::Null => eval_ast(rhs, env),
+            value => value,
+        },
+        Expression::BinaryOp {
+            lhs,
+            op: op @ (TokenType::And | TokenType::Or),
+            rhs,
+            line,
+        } => {
+            // the right hand side only runs when the left one doesn't already
+            // decide the result
+            let lhs = eval_ast(lhs, env);
+            let decided = *op == TokenType::Or;
+            let value = match lhs.truthy() {
+                Some(b) if b == decided => return Value::Bool(b),
+                Some(_) => eval_ast(rhs, env),
+                None => lhs,
+            };
+            env.borrow_mut().set_line(*line);
+            match value.truthy() {
+                Some(b) => Value::Bool(b),
+                None => {
+                    let message = format!(
+                        "Cannot apply '{}' to a value of type '{}'",
+                        op.symbol(),
+                        value.type_name()
+                    );
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        Expression::BinaryOp { lhs, op, rhs, line } => {
+            let lhs = eval_ast(lhs, env);
+            let rhs = eval_ast(rhs, env);
+            env.borrow_mut().set_line(*line);
+            eval_binary(lhs, *op, rhs, env)
+        }
+        Expression::UnaryOp { op, expr } => {
+            let value = eval_expr(expr, env);
+            match (op, value.truthy()) {
+                (TokenType::Sub, _) if number::float(&value).is_some() => {
+                    match number::negate(&value) {
+                        Ok(value) => value,
+                        Err(message) => {
+                            env.borrow_mut().throw_error(message);
+                            Value::Null
                         }
                     }
-                    _ => todo!(),
+                }
+                (TokenType::Not, Some(b)) => Value::Bool(!b),
+                (TokenType::Tilde, _) if number::integer(&value).is_some() => {
+                    number::complement(&value).unwrap_or(Value::Null)
+                }
+                (op, _) => {
+                    let message = format!(
+                        "Cannot apply '{}' to a value of type '{}'",
+                        op.symbol(),
+                        value.type_name()
+                    );
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        Expression::CallExpr { name, params, line } => eval_call_expr(name, params, *line, env),
+        Expression::Call {
+            callee,
+            params,
+            line,
+        } => {
+            let function = eval_expr(callee, env);
+            env.borrow_mut().set_line(*line);
+            let args = match eval_args(params, env) {
+                Some(args) => args,
+                None => return Value::Null,
+            };
+            env.borrow_mut().set_line(*line);
+            if env.borrow().is_raised() {
+                return Value::Null;
+            }
+            if !matches!(function, Value::Function { .. } | Value::Builtin(_)) {
+                let message = format!(
+                    "This value is not a function, it's a value of type '{}'",
+                    function.type_name()
+                );
+                env.borrow_mut().throw_error(message);
+                return Value::Null;
+            }
+            call_function("<function>", &function, args, env)
+        }
+        Expression::Block(body) => eval_block(body, env),
+        Expression::If {
+            cond,
+            then_branch,
+            else_branch,
+            line,
+        } => {
+            let cond = eval_expr(cond, env);
+            env.borrow_mut().set_line(*line);
+            match cond.truthy() {
+                Some(true) => eval_block(then_branch, env),
+                Some(false) => match else_branch {
+                    Some(else_branch) => eval_expr(else_branch, env),
+                    None => Value::Null,
+                },
+                None => {
+                    let message = format!(
+                        "Condition of 'if' must be a bool, found a value of type '{}'",
+                        cond.type_name()
+                    );
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        Expression::Try {
+            body,
+            error_name,
+            handler,
+        } => {
+            let value = eval_block(body, env);
+            let raised = env.borrow_mut().take_raised();
+            match raised {
+                Some((message, _)) => {
+                    let binding = error_name
+                        .clone()
+                        .map(|name| (name, Value::String(message)));
+                    eval_block_with(handler, env, binding)
+                }
+                None => value,
+            }
+        }
+        Expression::Array(items) => {
+            Value::Array(items.iter().map(|item| eval_expr(item, env)).collect())
+        }
+        Expression::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|(key, value)| (eval_expr(key, env), eval_expr(value, env)))
+                .collect();
+            match collection::make_map(entries) {
+                Ok(map) => map,
+                Err(message) => {
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        Expression::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        } => eval_range(start, end, *inclusive, step.as_deref(), env),
+        Expression::Index {
+            target,
+            index,
+            safe,
+            line,
+        } => {
+            let target = eval_expr(target, env);
+            if *safe && target == Value::Null {
+                return Value::Null;
+            }
+            let index = eval_expr(index, env);
+            env.borrow_mut().set_line(*line);
+            match collection::index(&target, &index) {
+                Ok(value) => value,
+                Err(message) => {
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
                 }
             }
-            "input" => {
-                if var.is_none() {
-                    println!("`input` requires a variable to store to");
+        }
+        Expression::Cast { value, ty, line } => {
+            let value = eval_expr(value, env);
+            env.borrow_mut().set_line(*line);
+            match number::cast(&value, ty) {
+                Ok(value) => value,
+                Err(message) => {
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
                 }
-                let mut buffer = String::new();
-                io::stdin()
-                    .read_line(&mut buffer)
-                    .expect("Failed to read line");
-                let buffer = buffer.trim().to_string();
+            }
+        }
+        Expression::Lambda {
+            params,
+            body,
+            ret_type,
+        } => Value::Function {
+            params: params.clone(),
+            body: body.clone(),
+            ret_type: ret_type.clone(),
+            env: Captured(env.clone()),
+        },
+    }
+}
 
-                let var = var.unwrap();
-                match var {
-                    Statement::VariableAssignment {
-                        constant,
-                        name,
-                        value: _,
-                    } => env.declare_variable(
-                        format!("{}", &name),
-                        Expression::StringLiteral(buffer),
-                        *constant,
-                    ),
-                    _ => {
-                        println!("Idk how this error happens, but if someone gets it, explain what you did please");
-                        std::process::exit(1)
-                    }
+fn eval_range(
+    start: &Expression,
+    end: &Expression,
+    inclusive: bool,
+    step: Option<&Expression>,
+    env: &Scope,
+) -> Value {
+    let start = eval_expr(start, env);
+    let end = eval_expr(end, env);
+    let step = step.map(|step| eval_expr(step, env));
+
+    for bound in [Some(&start), Some(&end), step.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        if number::float(bound).is_none() {
+            let message = format!(
+                "Ranges can only be made of numbers, found a value of type '{}'",
+                bound.type_name()
+            );
+            env.borrow_mut().throw_error(message);
+            return Value::Null;
+        }
+    }
+    if step.as_ref().and_then(number::float) == Some(0.0) {
+        env.borrow_mut()
+            .throw_error("The step of a range can't be zero".to_string());
+        return Value::Null;
+    }
+
+    Value::Range {
+        start: Box::new(start),
+        end: Box::new(end),
+        inclusive,
+        step: step.map(Box::new),
+    }
+}
+
+/// Evaluates a block in a new scope, passing a `return` hit inside of it on to
+/// the enclosing scope
+fn eval_block(body: &FunctionBody, env: &Scope) -> Value {
+    eval_block_with(body, env, None)
+}
+
+/// Like `eval_block`, declaring `binding` in the block's scope first
+fn eval_block_with(body: &FunctionBody, env: &Scope, binding: Option<(String, Value)>) -> Value {
+    let scope = Rc::new(RefCell::new(Environment::child(env.clone())));
+    if let Some((name, value)) = binding {
+        scope.borrow_mut().declare_variable(name, value, false);
+    }
+    let value = eval_body(body, &scope);
+
+    let returning = scope.borrow_mut().returning.take();
+    if returning.is_some() {
+        env.borrow_mut().returning = returning;
+    }
+    let jumping = scope.borrow_mut().jumping.take();
+    if jumping.is_some() {
+        env.borrow_mut().jumping = jumping;
+    }
+    value
+}
+
+fn eval_ast(node: &Ast, env: &Scope) -> Value {
+    match node {
+        Ast::Expression(expr) => eval_expr(expr, env),
+        Ast::Statement(_) => Value::Null,
+    }
+}
+
+/// `lhs op rhs` for every operator but `&&`, `||` and `??`, which decide
+/// whether to evaluate their right hand side themselves
+fn eval_binary(lhs: Value, op: TokenType, rhs: Value, env: &Scope) -> Value {
+    let result = match number::arithmetic(&lhs, op, &rhs, Overflow::Check) {
+        Some(result) => result,
+        None => match (lhs, op, rhs) {
+            (lhs, TokenType::EqEq, rhs) => Ok(Value::Bool(lhs == rhs)),
+            (lhs, TokenType::Ne, rhs) => Ok(Value::Bool(lhs != rhs)),
+            (Value::String(lhs), TokenType::Add, Value::String(rhs)) => {
+                Ok(Value::String(lhs + &rhs))
+            }
+            (lhs, TokenType::Lt | TokenType::Gt | TokenType::LtEq | TokenType::GtEq, rhs)
+                if comparable(&lhs, &rhs) =>
+            {
+                // every comparison with `NaN` is false
+                let ordering = lhs.compare(&rhs);
+                let holds = match op {
+                    TokenType::Lt => ordering.is_some_and(Ordering::is_lt),
+                    TokenType::Gt => ordering.is_some_and(Ordering::is_gt),
+                    TokenType::LtEq => ordering.is_some_and(Ordering::is_le),
+                    _ => ordering.is_some_and(Ordering::is_ge),
                 };
+                Ok(Value::Bool(holds))
+            }
+            (lhs, TokenType::In, rhs) => collection::contains(&rhs, &lhs).map(Value::Bool),
+            (lhs, op, rhs) => Err(format!(
+                "Cannot apply '{}' to values of type '{}' and '{}'",
+                op.symbol(),
+                lhs.type_name(),
+                rhs.type_name()
+            )),
+        },
+    };
+
+    match result {
+        Ok(value) => value,
+        Err(message) => {
+            env.borrow_mut().throw_error(message);
+            Value::Null
+        }
+    }
+}
+
+/// Whether `<` and friends can compare two values, which have to be two
+/// numbers or two strings
+fn comparable(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::String(_), Value::String(_)) => true,
+        (lhs, rhs) => number::float(lhs).is_some() && number::float(rhs).is_some(),
+    }
+}
+
+pub fn eval_call_expr(name: &str, params: &[Argument], line: usize, env: &Scope) -> Value {
+    env.borrow_mut().set_line(line);
+    let args = match eval_args(params, env) {
+        Some(args) => args,
+        None => return Value::Null,
+    };
+    env.borrow_mut().set_line(line);
+    if env.borrow().is_raised() {
+        return Value::Null;
+    }
+
+    if let Some((module_name, member)) = name.split_once("::") {
+        let module = env.borrow().get_module(module_name);
+        let module = match module {
+            Some(module) => module,
+            None => {
+                let message = format!("Cannot find module '{}' in scope", module_name);
+                env.borrow_mut().throw_error(message);
+                return Value::Null;
+            }
+        };
+
+        if let Some(lib_function) = module.get_lib_function(member) {
+            return call_lib_function(name, lib_function, args, env);
+        }
+
+        return match module.variables.get(member) {
+            Some(function) => call_function(name, function, args, env),
+            None => {
+                let message = format!(
+                    "Module '{}' has no exported function '{}'",
+                    module_name, member
+                );
+                env.borrow_mut().throw_error(message);
+                Value::Null
+            }
+        };
+    }
+
+    let lib_function = env.borrow().get_lib_function(name);
+    if let Some(lib_function) = lib_function {
+        return call_lib_function(name, &lib_function, args, env);
+    }
+
+    let function = env.borrow().get_variable(name);
+    match function {
+        Some(function) => call_function(name, &function, args, env),
+        None => {
+            let message = format!("Cannot find function '{}' in scope", name);
+            env.borrow_mut().throw_error(message);
+            Value::Null
+        }
+    }
+}
+
+/// Finds the function the interpreter provides under `name`, either a built
+/// in or `module::member` of a library module
+fn find_lib_function(name: &str, env: &Scope) -> Option<LibFunction> {
+    match name.split_once("::") {
+        Some((module, member)) => env
+            .borrow()
+            .get_module(module)?
+            .get_lib_function(member)
+            .cloned(),
+        None => env.borrow().get_lib_function(name),
+    }
+}
+
+/// Calls a function provided by the interpreter, whether it's called by name
+/// or was passed around as a value
+fn call_lib_function(name: &str, lib_function: &LibFunction, args: Vec<Arg>, env: &Scope) -> Value {
+    let args = match lib_args(name, lib_function, args, env) {
+        Some(args) => args,
+        None => return Value::Null,
+    };
+    if name.contains("::") {
+        return eval_lib_module_call(name, &args, env);
+    }
+
+    match name {
+        "print" | "println" => {
+            let output: Vec<String> = args.iter().map(Value::to_string).collect();
+            let output = output.join(" ");
+            if name == "println" {
+                println!("{}", output)
+            } else {
+                print!("{}", output)
+            }
+            Value::Null
+        }
+        "to_array" => match args.into_iter().next().map(collection::iterate) {
+            Some(Ok(items)) => Value::Array(items),
+            Some(Err(message)) => {
+                env.borrow_mut().throw_error(message);
+                Value::Null
+            }
+            None => Value::Array(Vec::new()),
+        },
+        "input" => {
+            if let Some(Value::String(prompt)) = args.first() {
+                print!("{}", prompt);
+                io::stdout().flush().expect("Failed to flush stdout");
+            }
+            let mut buffer = String::new();
+            match io::stdin().read_line(&mut buffer) {
+                Ok(_) => Value::String(buffer.trim().to_string()),
+                Err(err) => {
+                    env.borrow_mut()
+                        .throw_error(format!("Could not read input: {}", err));
+                    Value::Null
+                }
+            }
+        }
+        "wrapping_add" | "wrapping_sub" | "wrapping_mul" | "saturating_add" | "saturating_sub"
+        | "saturating_mul" => {
+            let (overflow, op) = match name.split_once('_') {
+                Some(("wrapping", op)) => (Overflow::Wrap, op),
+                Some((_, op)) => (Overflow::Saturate, op),
+                None => unreachable!(),
+            };
+            let op = match op {
+                "add" => TokenType::Add,
+                "sub" => TokenType::Sub,
+                _ => TokenType::Mul,
+            };
+            let result = match (number::integer(&args[0]), number::integer(&args[1])) {
+                (Some(_), Some(_)) => number::arithmetic(&args[0], op, &args[1], overflow),
+                _ => None,
+            };
+            match result {
+                Some(Ok(value)) => value,
+                _ => {
+                    let message = format!(
+                        "'{}' takes two integers, found values of type '{}' and '{}'",
+                        name,
+                        args[0].type_name(),
+                        args[1].type_name()
+                    );
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            }
+        }
+        "panic" => {
+            let message = args.first().map(Value::to_string).unwrap_or_default();
+            env.borrow_mut().throw_error(message);
+            Value::Null
+        }
+        "read_file" => match args.first() {
+            Some(Value::String(path)) => match fs::read_to_string(path) {
+                Ok(contents) => Value::String(contents),
+                Err(err) => {
+                    let message = format!("Could not read file '{}': {}", path, err);
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            },
+            arg => {
+                let message = format!(
+                    "'read_file' takes the path of a file as a string, found a value of type '{}'",
+                    arg.map_or("void".to_string(), Value::type_name)
+                );
+                env.borrow_mut().throw_error(message);
+                Value::Null
+            }
+        },
+        _ => unimplemented!(),
+    }
+}
+
+/// Evaluates the arguments of a call, expanding `...xs` into one positional
+/// argument per item of an array, range or string
+fn eval_args(params: &[Argument], env: &Scope) -> Option<Vec<Arg>> {
+    let mut args = Vec::new();
+    for arg in params {
+        let value = eval_expr(&arg.value, env);
+        match (arg.spread, value) {
+            (false, value) => args.push(Arg {
+                name: arg.name.clone(),
+                value,
+            }),
+            (true, value) => match collection::iterate(value) {
+                Ok(items) => args.extend(items.into_iter().map(|value| Arg { name: None, value })),
+                Err(message) => {
+                    env.borrow_mut().throw_error(message);
+                    return None;
+                }
+            },
+        }
+    }
+
+    Some(args)
+}
+
+/// Checks the arguments of a call to a library function against its
+/// signature, filling in the defaults of the parameters that weren't given one
+fn lib_args(name: &str, function: &LibFunction, args: Vec<Arg>, env: &Scope) -> Option<Vec<Value>> {
+    let mut args = positional_args(name, args, env)?;
+    let variadic = function.params.last().is_some_and(|param| param.variadic);
+    if args.len() > function.params.len() && !variadic {
+        let message = format!(
+            "Function '{}' takes at most {} argument(s) but {} were given",
+            name,
+            function.params.len(),
+            args.len()
+        );
+        env.borrow_mut().throw_error(message);
+        return None;
+    }
+
+    for param in function.params.iter().skip(args.len()) {
+        match &param.default {
+            Some(default) => args.push(eval_expr(default, env)),
+            None if param.variadic => {}
+            None => {
+                let message = format!(
+                    "Missing argument for parameter '{}' in call to function '{}'",
+                    param.name, name
+                );
+                env.borrow_mut().throw_error(message);
+                return None;
             }
-            _ => unimplemented!(),
         }
     }
+    Some(args)
+}
+
+/// Library functions only take positional arguments
+fn positional_args(name: &str, args: Vec<Arg>, env: &Scope) -> Option<Vec<Value>> {
+    if let Some(arg) = args.iter().find(|arg| arg.name.is_some()) {
+        let message = format!(
+            "Library function '{}' doesn't take named arguments, but got '{}'",
+            name,
+            arg.name.as_deref().unwrap_or_default()
+        );
+        env.borrow_mut().throw_error(message);
+        return None;
+    }
+
+    Some(args.into_iter().map(|arg| arg.value).collect())
+}
+
+/// Matches the arguments of a call to the parameters of function `name`,
+/// positional ones in order and named ones by name. Parameters that weren't
+/// given an argument are left as `None`, to be filled in by their default
+fn bind_args(
+    name: &str,
+    params: &[Param],
+    args: Vec<Arg>,
+    env: &Scope,
+) -> Option<Vec<Option<Value>>> {
+    let mut slots: Vec<Option<Value>> = vec![None; params.len()];
+    let variadic = params.last().is_some_and(|p| p.variadic);
+    let fixed = params.len() - variadic as usize;
+    let positional = args.iter().filter(|arg| arg.name.is_none()).count();
+
+    if positional > fixed && !variadic {
+        let message = format!(
+            "Function '{}' takes {} argument(s) but {} were given",
+            name,
+            params.len(),
+            positional
+        );
+        env.borrow_mut().throw_error(message);
+        return None;
+    }
+
+    // every positional argument past the fixed parameters is collected into
+    // the variadic one, which is an empty array when there are none
+    let mut rest = Vec::new();
+    for (index, arg) in args.into_iter().enumerate() {
+        let index = match &arg.name {
+            None if index >= fixed => {
+                rest.push(arg.value);
+                continue;
+            }
+            None => index,
+            Some(arg_name) => match params.iter().position(|p| &p.name == arg_name) {
+                Some(index) => index,
+                None => {
+                    let message =
+                        format!("Function '{}' has no parameter named '{}'", name, arg_name);
+                    env.borrow_mut().throw_error(message);
+                    return None;
+                }
+            },
+        };
+
+        if slots[index].is_some() || (index == fixed && !rest.is_empty()) {
+            let message = format!(
+                "Parameter '{}' of function '{}' was given more than one argument",
+                params[index].name, name
+            );
+            env.borrow_mut().throw_error(message);
+            return None;
+        }
+        slots[index] = Some(arg.value);
+    }
+    if variadic && slots[fixed].is_none() {
+        slots[fixed] = Some(Value::Array(rest));
+    }
+
+    if let Some((param, _)) = params
+        .iter()
+        .zip(&slots)
+        .find(|(param, slot)| slot.is_none() && param.default.is_none())
+    {
+        let message = format!(
+            "Missing argument for parameter '{}' in call to function '{}'",
+            param.name, name
+        );
+        env.borrow_mut().throw_error(message);
+        return None;
+    }
+
+    Some(slots)
+}
+
+/// Calls a function value, binding `args` to its parameters in a new scope
+/// nested inside the scope the function was defined in
+fn call_function(name: &str, function: &Value, args: Vec<Arg>, env: &Scope) -> Value {
+    let (params, body, ret_type, captured) = match function {
+        Value::Function {
+            params,
+            body,
+            ret_type,
+            env,
+        } => (params, body, ret_type, env),
+        Value::Builtin(builtin) => {
+            return match find_lib_function(builtin, env) {
+                Some(lib_function) => call_lib_function(builtin, &lib_function, args, env),
+                None => {
+                    let message = format!("Cannot find function '{}' in scope", builtin);
+                    env.borrow_mut().throw_error(message);
+                    Value::Null
+                }
+            };
+        }
+        _ => {
+            let message = format!("'{}' is not a function", name);
+            env.borrow_mut().throw_error(message);
+            return Value::Null;
+        }
+    };
+
+    let args = match bind_args(name, params, args, env) {
+        Some(args) => args,
+        None => return Value::Null,
+    };
+
+    // type parameters are bound to the types of the first arguments passed
+    // for them, every later use has to agree
+    let mut bindings = HashMap::new();
+    let scope = Rc::new(RefCell::new(Environment::child(captured.0.clone())));
+    for (param, arg) in params.iter().zip(args) {
+        // defaults can refer to the parameters before them
+        let arg = match (arg, &param.default) {
+            (Some(arg), _) => arg,
+            (None, Some(default)) => eval_expr(default, &scope),
+            (None, None) => Value::Null,
+        };
+
+        if !arg.matches_generic(&param.binding_type(), &mut bindings) {
+            let message = format!(
+                "Mismatched types: parameter '{}' of function '{}' is of type '{}' but the argument is of type '{}'",
+                param.name,
+                name,
+                param.binding_type().instantiate(&bindings),
+                arg.type_name()
+            );
+            env.borrow_mut().throw_error(message);
+            return Value::Null;
+        }
+
+        let ty = param.binding_type().instantiate(&bindings);
+        let arg = number::convert(arg, &ty);
+        scope
+            .borrow_mut()
+            .declare_variable(param.name.to_string(), arg, false);
+        if !ty.contains_param() {
+            scope.borrow_mut().declare_type(&param.name, ty);
+        }
+    }
+
+    if !env.borrow_mut().enter_call() {
+        return Value::Null;
+    }
+    let value = eval_body(body, &scope);
+    env.borrow_mut().leave_call();
+
+    // a function from another module raises its errors in that module's
+    // outermost scope, they're passed on to the caller's
+    let raised = scope.borrow_mut().take_raised();
+    if let Some((message, line)) = raised {
+        env.borrow_mut().raise(message, line);
+        return Value::Null;
+    }
+
+    let ret_type = ret_type.instantiate(&bindings);
+    if ret_type != Type::Void
+        && !matches!(ret_type, Type::Param(_))
+        && !value.matches_type(&ret_type)
+    {
+        let message = format!(
+            "Mismatched types: function '{}' should return '{}' but returned a value of type '{}'",
+            name,
+            ret_type,
+            value.type_name()
+        );
+        env.borrow_mut().throw_error(message);
+        return Value::Null;
+    }
+
+    number::convert(value, &ret_type)
+}
+
+fn eval_lib_module_call(path: &str, params: &[Value], env: &Scope) -> Value {
+    let mut args = Vec::new();
+    for param in params {
+        match number::float(param) {
+            Some(num) => args.push(num as f32),
+            None => {
+                let message = format!("Arguments to '{}' must be numbers", path);
+                env.borrow_mut().throw_error(message);
+                return Value::Null;
+            }
+        }
+    }
+
+    let result = match (path, args.as_slice()) {
+        ("math::sqrt", [x]) => x.sqrt(),
+        ("math::abs", [x]) => x.abs(),
+        ("math::floor", [x]) => x.floor(),
+        ("math::ceil", [x]) => x.ceil(),
+        ("math::pow", [x, y]) => x.powf(*y),
+        ("math::min", [x, y]) => x.min(*y),
+        ("math::max", [x, y]) => x.max(*y),
+        _ => {
+            let message = format!(
+                "Wrong number of arguments passed to '{}' ({} given)",
+                path,
+                args.len()
+            );
+            env.borrow_mut().throw_error(message);
+            return Value::Null;
+        }
+    };
+
+    Value::Float(result)
 }
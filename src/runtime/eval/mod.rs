@@ -1 +1,4 @@
+pub mod collection;
 pub mod expr;
+pub mod number;
+pub mod stmt;
@@ -0,0 +1,452 @@
+use std::cmp::Ordering;
+
+use crate::runtime::value::Value;
+use crate::syntax::lexer::{TokenType, Type};
+
+/// What happens when the result of integer arithmetic doesn't fit in its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Raises an error, used by the operators
+    Check,
+    /// Wraps around, used by `wrapping_add` and friends
+    Wrap,
+    /// Stops at the smallest or largest value, used by `saturating_add` and
+    /// friends
+    Saturate,
+}
+
+/// The value of an integer, or `None` for anything else
+pub fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Short(n) => Some(*n as i64),
+        Value::Int(n) => Some(*n as i64),
+        Value::Large(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// The value of any number as a float, or `None` for anything else
+pub fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(n) => Some(*n as f64),
+        value => integer(value).map(|n| n as f64),
+    }
+}
+
+/// The integer type a whole number is stored as when nothing asks for a
+/// specific one, i.e. `int` unless it's too large for it
+pub fn whole(n: i64) -> Value {
+    match i32::try_from(n) {
+        Ok(n) => Value::Int(n),
+        Err(_) => Value::Large(n),
+    }
+}
+
+/// `lhs op rhs` for two numbers. Two integers give an integer of the wider of
+/// their types, where `/` rounds towards zero, anything else gives a float.
+/// The bitwise operators only work on integers. Returns `None` when the
+/// operator can't be applied to the values
+pub fn arithmetic(
+    lhs: &Value,
+    op: TokenType,
+    rhs: &Value,
+    overflow: Overflow,
+) -> Option<Result<Value, String>> {
+    if let (Some(a), Some(b)) = (integer(lhs), integer(rhs)) {
+        let ty = wider(&width(lhs), &width(rhs));
+        let (a, b) = (a as i128, b as i128);
+        let result = match op {
+            TokenType::Add => Some(a + b),
+            TokenType::Sub => Some(a - b),
+            TokenType::Mul => Some(a * b),
+            TokenType::Div | TokenType::Mod if b == 0 => {
+                return Some(Err(format!(
+                    "Division by zero: {} {} {}",
+                    a,
+                    op.symbol(),
+                    b
+                )))
+            }
+            TokenType::Div => Some(a / b),
+            TokenType::Mod => Some(a % b),
+            TokenType::BitwiseAnd => Some(a & b),
+            TokenType::BitwiseXor => Some(a ^ b),
+            TokenType::BitwiseOr => Some(a | b),
+            // shifting by the type's width or more, or by a negative amount,
+            // has no sensible result
+            TokenType::ShiftLeft | TokenType::ShiftRight if !(0..bits(&ty)).contains(&b) => {
+                return Some(Err(format!(
+                    "Cannot shift a value of type '{}' by {} bits, it has {}",
+                    ty,
+                    b,
+                    bits(&ty)
+                )))
+            }
+            TokenType::ShiftLeft => Some(a << b),
+            TokenType::ShiftRight => Some(a >> b),
+            _ => None,
+        }?;
+        return Some(fit(result, &ty, overflow).ok_or_else(|| {
+            format!(
+                "Integer overflow: {} {} {} doesn't fit in type '{}'",
+                a,
+                op.symbol(),
+                b,
+                ty
+            )
+        }));
+    }
+
+    let (a, b) = (float(lhs)? as f32, float(rhs)? as f32);
+    if matches!(op, TokenType::Div | TokenType::Mod) && b == 0.0 {
+        return Some(Err(format!(
+            "Division by zero: {} {} {}",
+            lhs,
+            op.symbol(),
+            rhs
+        )));
+    }
+    let result = match op {
+        TokenType::Add => a + b,
+        TokenType::Sub => a - b,
+        TokenType::Mul => a * b,
+        TokenType::Div => a / b,
+        TokenType::Mod => a % b,
+        _ => return None,
+    };
+    Some(Ok(Value::Float(result)))
+}
+
+/// `-value` for a number, where negating the smallest value of an integer type
+/// overflows
+pub fn negate(value: &Value) -> Result<Value, String> {
+    if let Value::Float(n) = value {
+        return Ok(Value::Float(-n));
+    }
+    let n = integer(value)
+        .ok_or_else(|| format!("Cannot negate a value of type '{}'", value.type_name()))?;
+    let ty = width(value);
+    fit(-(n as i128), &ty, Overflow::Check)
+        .ok_or_else(|| format!("Integer overflow: -({}) doesn't fit in type '{}'", n, ty))
+}
+
+/// `~value` for an integer, flipping every bit of it
+pub fn complement(value: &Value) -> Option<Value> {
+    let n = integer(value)?;
+    fit(!n as i128, &width(value), Overflow::Check)
+}
+
+/// How two numbers are ordered, comparing integers exactly
+pub fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    match (integer(lhs), integer(rhs)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => float(lhs)?.partial_cmp(&float(rhs)?),
+    }
+}
+
+/// Stores a number as the numeric type `ty`, for values that already passed
+/// `matches_type`, e.g. the `5` in `x: short := 5`. Collections are converted
+/// item by item and everything else is left as it is
+pub fn convert(value: Value, ty: &Type) -> Value {
+    match (value, ty) {
+        (value @ Value::Null, _) => value,
+        (value, Type::Optional(ty)) => convert(value, ty),
+        (Value::Array(items), Type::Array(ty)) => {
+            Value::Array(items.into_iter().map(|item| convert(item, ty)).collect())
+        }
+        (Value::Map(entries), Type::Map(key_ty, value_ty)) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (convert(key, key_ty), convert(value, value_ty)))
+                .collect(),
+        ),
+        (value, Type::Short | Type::Int | Type::Large) => match integer(&value) {
+            Some(n) => fit(n as i128, ty, Overflow::Check).unwrap_or(value),
+            None => value,
+        },
+        (value, Type::Float) => match float(&value) {
+            Some(n) => Value::Float(n as f32),
+            None => value,
+        },
+        (value, _) => value,
+    }
+}
+
+/// `value as ty`. Numbers convert between each other, where floats are
+/// rounded towards zero and have to fit in the integer type. Anything can
+/// become a string, and strings can be parsed as numbers and bools
+pub fn cast(value: &Value, ty: &Type) -> Result<Value, String> {
+    let cannot = || {
+        format!(
+            "Cannot cast a value of type '{}' to '{}'",
+            value.type_name(),
+            ty
+        )
+    };
+
+    match (value, ty) {
+        (_, Type::String) => Ok(Value::String(value.to_string())),
+        (Value::String(string), _) => {
+            let parsed = match ty {
+                Type::Short => string.trim().parse().ok().map(Value::Short),
+                Type::Int => string.trim().parse().ok().map(Value::Int),
+                Type::Large => string.trim().parse().ok().map(Value::Large),
+                Type::Float => string.trim().parse().ok().map(Value::Float),
+                Type::Bool => string.trim().parse().ok().map(Value::Bool),
+                _ => return Err(cannot()),
+            };
+            parsed.ok_or_else(|| format!("Cannot cast '{}' to '{}'", string, ty))
+        }
+        (Value::Bool(_), Type::Bool) => Ok(value.clone()),
+        (value, Type::Float) => match float(value) {
+            Some(n) => Ok(Value::Float(n as f32)),
+            None => Err(cannot()),
+        },
+        (value, Type::Short | Type::Int | Type::Large) => {
+            let n = match (value, integer(value)) {
+                (_, Some(n)) => n as i128,
+                (Value::Float(n), None) if n.is_finite() => n.trunc() as i128,
+                (Value::Float(n), None) => return Err(format!("Cannot cast {} to '{}'", n, ty)),
+                _ => return Err(cannot()),
+            };
+            fit(n, ty, Overflow::Check)
+                .ok_or_else(|| format!("Cannot cast {} to '{}', it doesn't fit in it", value, ty))
+        }
+        _ => Err(cannot()),
+    }
+}
+
+/// The integer type of an integer value
+fn width(value: &Value) -> Type {
+    match value {
+        Value::Short(_) => Type::Short,
+        Value::Large(_) => Type::Large,
+        _ => Type::Int,
+    }
+}
+
+/// How many bits an integer type has
+fn bits(ty: &Type) -> i128 {
+    match ty {
+        Type::Short => 16,
+        Type::Int => 32,
+        _ => 64,
+    }
+}
+
+fn wider(a: &Type, b: &Type) -> Type {
+    match (a, b) {
+        (Type::Large, _) | (_, Type::Large) => Type::Large,
+        (Type::Int, _) | (_, Type::Int) => Type::Int,
+        _ => Type::Short,
+    }
+}
+
+/// Stores the exact result of integer arithmetic as the integer type `ty`,
+/// handling a result that's out of its range according to `overflow`
+fn fit(n: i128, ty: &Type, overflow: Overflow) -> Option<Value> {
+    let (min, max) = match ty {
+        Type::Short => (i16::MIN as i128, i16::MAX as i128),
+        Type::Int => (i32::MIN as i128, i32::MAX as i128),
+        _ => (i64::MIN as i128, i64::MAX as i128),
+    };
+    let n = match overflow {
+        _ if (min..=max).contains(&n) => n,
+        Overflow::Check => return None,
+        // the casts below truncate to the type's width, which is what wrapping
+        // around means for two's complement numbers
+        Overflow::Wrap => n,
+        Overflow::Saturate => n.clamp(min, max),
+    };
+    let value = match ty {
+        Type::Short => Value::Short(n as i16),
+        Type::Int => Value::Int(n as i32),
+        _ => Value::Large(n as i64),
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked(lhs: Value, op: TokenType, rhs: Value) -> Result<Value, String> {
+        arithmetic(&lhs, op, &rhs, Overflow::Check).expect("operator applies to numbers")
+    }
+
+    #[test]
+    fn xor_flips_the_bits_set_in_one_side() {
+        assert!(matches!(
+            checked(Value::Int(5), TokenType::BitwiseXor, Value::Int(3)),
+            Ok(Value::Int(6))
+        ));
+        assert!(matches!(
+            checked(Value::Int(-1), TokenType::BitwiseXor, Value::Int(5)),
+            Ok(Value::Int(-6))
+        ));
+        assert!(matches!(
+            checked(Value::Short(12), TokenType::BitwiseXor, Value::Short(10)),
+            Ok(Value::Short(6))
+        ));
+        assert!(matches!(
+            checked(Value::Short(1), TokenType::BitwiseXor, Value::Large(3)),
+            Ok(Value::Large(2))
+        ));
+    }
+
+    #[test]
+    fn bitwise_operators_only_take_integers() {
+        for op in [
+            TokenType::BitwiseAnd,
+            TokenType::BitwiseXor,
+            TokenType::BitwiseOr,
+        ] {
+            let result = arithmetic(&Value::Float(1.0), op, &Value::Int(1), Overflow::Check);
+            assert!(result.is_none(), "{:?} on a float gave {:?}", op, result);
+        }
+    }
+
+    #[test]
+    fn integers_widen_to_the_wider_type() {
+        assert!(matches!(
+            checked(Value::Short(1), TokenType::Add, Value::Int(2)),
+            Ok(Value::Int(3))
+        ));
+        assert!(matches!(
+            checked(Value::Int(1), TokenType::Mul, Value::Large(2)),
+            Ok(Value::Large(2))
+        ));
+        assert!(matches!(
+            checked(Value::Int(1), TokenType::Add, Value::Float(0.5)),
+            Ok(Value::Float(n)) if n == 1.5
+        ));
+    }
+
+    #[test]
+    fn overflow_is_checked_wrapped_or_saturated() {
+        let (max, one) = (Value::Int(i32::MAX), Value::Int(1));
+        let error = checked(max.clone(), TokenType::Add, one.clone()).unwrap_err();
+        assert!(error.starts_with("Integer overflow"), "{}", error);
+
+        let wrapped = arithmetic(&max, TokenType::Add, &one, Overflow::Wrap);
+        assert!(matches!(wrapped, Some(Ok(Value::Int(i32::MIN)))));
+        let saturated = arithmetic(&max, TokenType::Add, &one, Overflow::Saturate);
+        assert!(matches!(saturated, Some(Ok(Value::Int(i32::MAX)))));
+
+        let short = arithmetic(
+            &Value::Short(i16::MIN),
+            TokenType::Sub,
+            &Value::Short(1),
+            Overflow::Wrap,
+        );
+        assert!(matches!(short, Some(Ok(Value::Short(i16::MAX)))));
+    }
+
+    #[test]
+    fn division_rounds_towards_zero() {
+        assert!(matches!(
+            checked(Value::Int(-7), TokenType::Div, Value::Int(2)),
+            Ok(Value::Int(-3))
+        ));
+        assert!(matches!(
+            checked(Value::Int(-7), TokenType::Mod, Value::Int(2)),
+            Ok(Value::Int(-1))
+        ));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        for (lhs, rhs) in [
+            (Value::Int(1), Value::Int(0)),
+            (Value::Float(1.0), Value::Float(0.0)),
+            (Value::Int(1), Value::Float(0.0)),
+        ] {
+            for op in [TokenType::Div, TokenType::Mod] {
+                let error = checked(lhs.clone(), op, rhs.clone()).unwrap_err();
+                assert!(error.starts_with("Division by zero"), "{}", error);
+            }
+        }
+    }
+
+    #[test]
+    fn shifts_stay_within_the_width_of_the_type() {
+        assert!(matches!(
+            checked(Value::Int(1), TokenType::ShiftLeft, Value::Int(30)),
+            Ok(Value::Int(1073741824))
+        ));
+        assert!(checked(Value::Int(1), TokenType::ShiftLeft, Value::Int(31)).is_err());
+        assert!(matches!(
+            checked(Value::Large(1), TokenType::ShiftLeft, Value::Int(40)),
+            Ok(Value::Large(1099511627776))
+        ));
+        assert!(matches!(
+            checked(Value::Int(-8), TokenType::ShiftRight, Value::Int(1)),
+            Ok(Value::Int(-4))
+        ));
+
+        for amount in [-1, 32] {
+            let error =
+                checked(Value::Int(1), TokenType::ShiftLeft, Value::Int(amount)).unwrap_err();
+            assert!(error.starts_with("Cannot shift"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn negating_the_smallest_integer_overflows() {
+        assert!(negate(&Value::Short(i16::MIN)).is_err());
+        assert!(matches!(negate(&Value::Short(5)), Ok(Value::Short(-5))));
+        assert!(matches!(complement(&Value::Int(5)), Some(Value::Int(-6))));
+    }
+
+    #[test]
+    fn integers_compare_exactly() {
+        let (a, b) = (Value::Large(i64::MAX), Value::Large(i64::MAX - 1));
+        assert_eq!(compare(&a, &b), Some(Ordering::Greater));
+        assert_eq!(compare(&Value::Float(f32::NAN), &Value::Int(1)), None);
+    }
+
+    #[test]
+    fn casts_between_numbers() {
+        assert!(matches!(
+            cast(&Value::Float(3.9), &Type::Int),
+            Ok(Value::Int(3))
+        ));
+        assert!(matches!(
+            cast(&Value::Float(-3.9), &Type::Int),
+            Ok(Value::Int(-3))
+        ));
+        assert!(cast(&Value::Float(f32::NAN), &Type::Int).is_err());
+        assert!(cast(&Value::Large(1 << 40), &Type::Int).is_err());
+        assert!(matches!(
+            cast(&Value::Int(300), &Type::Short),
+            Ok(Value::Short(300))
+        ));
+    }
+
+    #[test]
+    fn casts_to_and_from_strings() {
+        assert!(matches!(
+            cast(&Value::String(" 42 ".to_string()), &Type::Int),
+            Ok(Value::Int(42))
+        ));
+        assert!(cast(&Value::String("abc".to_string()), &Type::Int).is_err());
+        assert!(matches!(
+            cast(&Value::Bool(true), &Type::String),
+            Ok(Value::String(string)) if string == "true"
+        ));
+        assert!(cast(&Value::Bool(true), &Type::Int).is_err());
+    }
+
+    #[test]
+    fn convert_stores_items_as_the_declared_type() {
+        let converted = convert(
+            Value::Array(vec![Value::Int(1), Value::Null]),
+            &Type::Array(Box::new(Type::Optional(Box::new(Type::Short)))),
+        );
+        assert!(matches!(
+            converted,
+            Value::Array(items) if matches!(items.as_slice(), [Value::Short(1), Value::Null])
+        ));
+        assert!(matches!(convert(Value::Int(2), &Type::Float), Value::Float(n) if n == 2.0));
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use super::super::environment::{Captured, Environment, Jump, Scope};
+use super::collection;
+use super::expr::eval_expr;
+use super::number;
+use crate::runtime::value::Value;
+use crate::syntax::ast::{Expression, FunctionBody, Statement};
+use crate::syntax::lexer::{TokenType, Type};
+
+/// Evaluates a statement, returning the value of a `return` if one was hit
+pub fn eval_statement(stmt: &Statement, env: &Scope) -> Option<Value> {
+    match stmt {
+        Statement::VariableAssignment {
+            constant,
+            name,
+            ty,
+            value: expr,
+            line,
+        } => {
+            // expressions without a line of their own report errors at the
+            // statement, which calls inside them move away from
+            env.borrow_mut().set_line(*line);
+            let mut value = eval_expr(expr, env);
+            env.borrow_mut().set_line(*line);
+
+            if let Some(ty) = ty {
+                if matches!(expr, Expression::CallExpr { name, .. } if name == "input") {
+                    match parse_input(value, ty) {
+                        Ok(parsed) => value = parsed,
+                        Err(message) => {
+                            env.borrow_mut().throw_error(message);
+                            return None;
+                        }
+                    }
+                }
+
+                if !value.matches_type(ty) {
+                    let message = format!(
+                        "Mismatched types: variable '{}' is declared as '{}' but its value is of type '{}'",
+                        name,
+                        ty,
+                        value.type_name()
+                    );
+                    env.borrow_mut().throw_error(message);
+                    return None;
+                }
+                value = number::convert(value, ty);
+            }
+
+            let kept = ty.clone().or_else(|| literal_type(expr, &value));
+            env.borrow_mut()
+                .declare_variable(name.to_string(), value, *constant);
+            if let Some(ty) = kept {
+                env.borrow_mut().declare_type(name, ty);
+            }
+        }
+        Statement::Reassignment { name, value, line } => {
+            env.borrow_mut().set_line(*line);
+            let value = eval_expr(value, env);
+            env.borrow_mut().set_line(*line);
+            let result = env.borrow_mut().assign_variable(name, value);
+            if let Err(message) = result {
+                env.borrow_mut().throw_error(message);
+            }
+        }
+        Statement::Function {
+            name,
+            params,
+            body,
+            ret_type,
+            ..
+        } => {
+            let closure = Value::Function {
+                params: params.clone(),
+                body: body.clone(),
+                ret_type: ret_type.clone(),
+                env: Captured(env.clone()),
+            };
+            env.borrow_mut()
+                .declare_variable(name.to_string(), closure, false);
+        }
+        Statement::Return { value, line } => {
+            env.borrow_mut().set_line(*line);
+            let value = eval_expr(value, env);
+            // a `return` whose value raised an error doesn't return
+            if !env.borrow().is_raised() {
+                return Some(value);
+            }
+        }
+        Statement::IndexAssignment {
+            name,
+            indices,
+            value,
+            line,
+        } => {
+            env.borrow_mut().set_line(*line);
+            let indices: Vec<Value> = indices.iter().map(|i| eval_expr(i, env)).collect();
+            let value = eval_expr(value, env);
+            env.borrow_mut().set_line(*line);
+
+            let target = env.borrow().get_variable(name);
+            let mut target = match target {
+                Some(target) => target,
+                None => {
+                    let message = format!("Cannot find value '{}' in scope", name);
+                    env.borrow_mut().throw_error(message);
+                    return None;
+                }
+            };
+
+            let result = collection::set_index(&mut target, &indices, value)
+                .and_then(|_| env.borrow_mut().assign_variable(name, target));
+            if let Err(message) = result {
+                env.borrow_mut().throw_error(message);
+            }
+        }
+        Statement::For {
+            name,
+            value_name,
+            iterable,
+            body,
+            line,
+        } => {
+            env.borrow_mut().set_line(*line);
+            let iterable = eval_expr(iterable, env);
+            env.borrow_mut().set_line(*line);
+            let items = match value_name {
+                Some(_) => collection::iterate_pairs(iterable),
+                None => collection::iterate(iterable)
+                    .map(|items| items.into_iter().map(|item| (item, Value::Null)).collect()),
+            };
+            let items = match items {
+                Ok(items) => items,
+                Err(message) => {
+                    env.borrow_mut().throw_error(message);
+                    return None;
+                }
+            };
+
+            for (item, value) in items {
+                let scope = Rc::new(RefCell::new(Environment::child(env.clone())));
+                scope
+                    .borrow_mut()
+                    .declare_variable(name.to_string(), item, false);
+                if let Some(value_name) = value_name {
+                    scope
+                        .borrow_mut()
+                        .declare_variable(value_name.to_string(), value, false);
+                }
+                if let ControlFlow::Break(returning) = eval_round(body, &scope, env) {
+                    return returning;
+                }
+            }
+        }
+        Statement::Loop { body, .. } => loop {
+            let scope = Rc::new(RefCell::new(Environment::child(env.clone())));
+            if let ControlFlow::Break(returning) = eval_round(body, &scope, env) {
+                return returning;
+            }
+        },
+        Statement::Break { .. } => env.borrow_mut().jumping = Some(Jump::Break),
+        Statement::Continue { .. } => env.borrow_mut().jumping = Some(Jump::Continue),
+        Statement::ExprStmt(expr) => {
+            eval_expr(expr, env);
+        }
+        Statement::Attributed { stmt, .. } => return eval_statement(stmt, env),
+        Statement::ImportPath { .. } | Statement::Export(_) => {
+            let message = "Imports and exports are only allowed at the top level of a file";
+            env.borrow_mut().throw_error(message.to_string());
+        }
+    }
+
+    // a `return` inside a nested block ends the enclosing body too
+    env.borrow_mut().returning.take()
+}
+
+/// Runs one round of a loop's body in `scope`, breaking with what the loop
+/// statement returns when the loop should stop
+fn eval_round(body: &FunctionBody, scope: &Scope, env: &Scope) -> ControlFlow<Option<Value>> {
+    eval_body(body, scope);
+
+    let returning = scope.borrow_mut().returning.take();
+    if returning.is_some() {
+        return ControlFlow::Break(returning);
+    }
+    let jumping = scope.borrow_mut().jumping.take();
+    if env.borrow().is_raised() || jumping == Some(Jump::Break) {
+        return ControlFlow::Break(None);
+    }
+    ControlFlow::Continue(())
+}
+
+/// The type a variable declared without one keeps when its value makes it
+/// plain, i.e. a number literal like the `1.5` in `x := 1.5`
+fn literal_type(expr: &Expression, value: &Value) -> Option<Type> {
+    match expr {
+        Expression::Short(_) | Expression::Int(_) | Expression::Large(_) | Expression::Float(_) => {
+            value.value_type()
+        }
+        Expression::UnaryOp {
+            op: TokenType::Sub,
+            expr,
+        } => literal_type(expr, value),
+        _ => None,
+    }
+}
+
+/// Converts the line read by `input` into the type the variable was annotated
+/// with, e.g. `age: int := input();`
+fn parse_input(value: Value, ty: &Type) -> Result<Value, String> {
+    let line = match &value {
+        Value::String(line) => line.clone(),
+        _ => return Ok(value),
+    };
+
+    let parsed = match ty {
+        Type::Short | Type::Int | Type::Large | Type::Float | Type::Bool => {
+            number::cast(&value, ty).ok()
+        }
+        _ => return Ok(value),
+    };
+
+    parsed.ok_or_else(|| format!("Could not parse input '{}' as '{}'", line, ty))
+}
+
+/// Evaluates the statements of a function or block in `env`. When a `return`
+/// is hit its value is left in `env.returning` for blocks to pass outwards
+pub fn eval_body(body: &FunctionBody, env: &Scope) -> Value {
+    for stmt in &body.stmts {
+        if let Some(value) = eval_statement(stmt, env) {
+            env.borrow_mut().returning = Some(value.clone());
+            return value;
+        }
+        // a `break` or `continue` skips the rest of the loop's body
+        if env.borrow().is_raised() || env.borrow().jumping.is_some() {
+            return Value::Null;
+        }
+    }
+
+    match &body.value {
+        Some(value) => eval_expr(value, env),
+        None => Value::Null,
+    }
+}
@@ -1,39 +1,233 @@
-use super::environment::Environment;
-use super::eval::expr::*;
-use crate::syntax::ast::{Ast, Expression, Statement};
+use std::cell::RefCell;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
 
-use std::io;
+use super::environment::{Environment, Scope};
+use super::eval::expr::eval_expr;
+use super::eval::stmt;
+use super::module::ModuleLoader;
+use crate::error::VeloError;
+use crate::syntax::ast::{Ast, Statement};
 
-// TODO: Create a separate call expr function that just returns an expression
-// I'll use it when parsing variables probably
-pub fn evaluate(nodes: Vec<Ast>) {
-    let mut env = Environment::init();
+/// Runs a program, returning the errors that stopped it or one of the
+/// modules it imports
+pub fn evaluate(nodes: Vec<Ast>, file: Option<&Path>) -> Vec<VeloError> {
+    let mut loader = ModuleLoader::new(file);
+    let env = run(nodes, file, &mut loader);
+    let mut errors = mem::take(&mut env.borrow_mut().errors);
+    errors.append(&mut loader.errors);
+    errors
+}
+
+/// Evaluates `nodes` in a fresh environment. `file` is the path of the source
+/// being run and is used to resolve relative imports
+pub fn run(nodes: Vec<Ast>, file: Option<&Path>, loader: &mut ModuleLoader) -> Scope {
+    let env = Rc::new(RefCell::new(Environment::init()));
     for node in nodes {
         match node {
-            Ast::Expression(expr) => match expr {
-                Expression::CallExpr {
-                    ref name,
-                    ref params,
-                } => eval_call_expr(&expr, &mut env, None),
-                Expression::Null => {}
-                _ => unimplemented!(), // sticking out your gyat
-            },
-            Ast::Statement(stmt) => match stmt {
-                Statement::VariableAssignment {
-                    constant,
-                    ref name,
-                    ref value,
-                } => match value {
-                    Expression::CallExpr { name, params } => {
-                        eval_call_expr(&value, &mut env, Some(&stmt))
-                    }
-                    _ => {
-                        env.declare_variable(name.to_string(), value.clone(), constant);
-                    }
-                },
-                _ => todo!(),
-            },
+            Ast::Expression(expr) => {
+                eval_expr(&expr, &env);
+            }
+            Ast::Statement(stmt) => eval_statement(stmt, &env, file, loader),
+        }
+
+        // an error nothing caught ends the program
+        if env.borrow().is_raised() {
+            env.borrow_mut().report_raised();
+            break;
+        }
+    }
+    env
+}
+
+fn eval_statement(stmt: Statement, env: &Scope, file: Option<&Path>, loader: &mut ModuleLoader) {
+    match stmt {
+        Statement::ImportPath {
+            path,
+            is_library,
+            line,
+        } => {
+            env.borrow_mut().set_line(line);
+            let module = match is_library {
+                true => loader.load_library(&path),
+                false => loader.load_file(&path, file),
+            };
+            match module {
+                Ok(module) => env.borrow_mut().declare_module(module),
+                Err(message) => env.borrow_mut().throw_error(message),
+            }
+        }
+        Statement::Attributed { stmt, .. } => eval_statement(*stmt, env, file, loader),
+        Statement::Export(stmt) => {
+            if let Some(name) = stmt.declared_name() {
+                env.borrow_mut().exports.push(name.to_string());
+            }
+            eval_statement(*stmt, env, file, loader)
         }
+        _ => {
+            stmt::eval_statement(&stmt, env);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+    use crate::syntax::parse::Parser;
+
+    fn parse(source: &str) -> Vec<Ast> {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        Parser::new(&tokens)
+            .parse()
+            .unwrap_or_else(|errors| panic!("failed to parse {:?}: {:?}", source, errors))
+    }
+
+    /// Runs `source`, giving the values of `names` afterwards as they're printed
+    fn values_of(source: &str, names: &[&str]) -> Vec<String> {
+        let env = run(parse(source), None, &mut ModuleLoader::new(None));
+        let env = env.borrow();
+        if let Some(error) = env.errors.first() {
+            panic!("failed to run {:?}: {:?}", source, error);
+        }
+        names
+            .iter()
+            .map(|name| match env.get_variable(name) {
+                Some(value) => value.to_string(),
+                None => panic!("'{}' is not defined after running {:?}", name, source),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closures_keep_the_variables_they_were_made_in() {
+        let source = "
+            fun adder(n int) -> fun(int) -> int { fun(x int) -> int { x + n } }
+            add2 := adder(2);
+            a := add2(3);
+            b := adder(10)(1);
+        ";
+        assert_eq!(values_of(source, &["a", "b"]), ["5", "11"]);
+    }
+
+    #[test]
+    fn closures_share_the_variables_they_capture() {
+        let source = "
+            fun counter() -> fun() -> int {
+                count := 0;
+                fun() -> int { count = count + 1; count }
+            }
+            c := counter();
+            c(); c();
+            a := c();
+            n := 5;
+            get := fun() -> int { n };
+            n = 6;
+            b := get();
+        ";
+        assert_eq!(values_of(source, &["a", "b"]), ["3", "6"]);
+    }
+
+    #[test]
+    fn functions_are_values() {
+        let source = "
+            fun twice(f fun(int) -> int, x int) -> int { f(f(x)) }
+            sq := fun(x int) -> int { x * x };
+            fs := [sq];
+            a := twice(sq, 3);
+            b := fs[0](4);
+        ";
+        assert_eq!(values_of(source, &["a", "b"]), ["81", "16"]);
+    }
+
+    #[test]
+    fn defaults_can_use_the_parameters_before_them() {
+        let source = "
+            fun area(w int, h int = w, scale int = 1) -> int { w * h * scale }
+            a := area(3);
+            b := area(3, scale: 2);
+            c := area(h: 4, w: 2);
+        ";
+        assert_eq!(values_of(source, &["a", "b", "c"]), ["9", "18", "8"]);
+    }
+
+    #[test]
+    fn errors_in_a_value_are_reported_at_its_statement() {
+        let errors = evaluate(parse("x := 1;\nprintln(x);\ny := 1..5 step 0;"), None);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].line, 3);
+        assert!(errors[0]
+            .message
+            .contains("The step of a range can't be zero"));
+    }
+
+    #[test]
+    fn ranges_are_values() {
+        let source = "
+            r := 1..4;
+            a := to_array(r);
+            b := to_array(1..=4);
+            c := to_array(0..10 step 3);
+            d := to_array(5..1 step -2);
+            e := [3 in r, 4 in r];
+        ";
+        let expected = [
+            "1..4",
+            "[1, 2, 3]",
+            "[1, 2, 3, 4]",
+            "[0, 3, 6, 9]",
+            "[5, 3]",
+            "[true, false]",
+        ];
+        assert_eq!(values_of(source, &["r", "a", "b", "c", "d", "e"]), expected);
+    }
+
+    #[test]
+    fn ranges_slice_and_loop() {
+        let source = "
+            xs := [10, 20, 30, 40];
+            a := xs[1..3];
+            b := \"hello\"[1..=2];
+            c := 0;
+            for i in 1..=3 { c = c + i; }
+        ";
+        assert_eq!(values_of(source, &["a", "b", "c"]), ["[20, 30]", "el", "6"]);
+    }
+
+    #[test]
+    fn catch_gets_the_message_of_the_error() {
+        let source = "
+            fun risky(n int) -> int { if n > 2 { panic(\"too big\"); } n }
+            a := try { risky(1) + risky(3) } catch { -1 };
+            b := try { risky(2) } catch { -1 };
+            c := try { risky(5) } catch e { e };
+            d := try { [1][5] } catch e { e };
+        ";
+        let expected = [
+            "-1",
+            "2",
+            "too big",
+            "Index 5 is out of bounds for length 1",
+        ];
+        assert_eq!(values_of(source, &["a", "b", "c", "d"]), expected);
+    }
+
+    #[test]
+    fn errors_raised_in_catch_go_to_the_outer_try() {
+        let source = "
+            a := try {
+                try { panic(\"inner\"); 0 } catch e { panic(\"again: \" + e); 0 }
+            } catch e { e };
+        ";
+        assert_eq!(values_of(source, &["a"]), ["again: inner"]);
+
+        let errors = evaluate(parse("try { panic(\"a\"); } catch { panic(\"b\"); }"), None);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].message.contains("\x1b[1mb\x1b[0m"),
+            "{:?}",
+            errors
+        );
     }
-    println!("\n{:#?}", env)
 }
@@ -1,3 +1,6 @@
+pub mod builtins;
 pub mod environment;
 pub mod eval;
 pub mod interpreter;
+pub mod module;
+pub mod value;
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::builtins::library;
+use super::environment::{Environment, LibFunction};
+use super::interpreter::run;
+use super::value::Value;
+use crate::checker::check;
+use crate::error::VeloError;
+use crate::syntax::lexer::Lexer;
+use crate::syntax::parse::Parser;
+
+/// The exported members of an imported file or library, accessed as
+/// `name::member`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub variables: HashMap<String, Value>,
+    pub lib_functions: Vec<LibFunction>,
+}
+
+impl Module {
+    fn from_env(name: String, env: &Environment) -> Module {
+        let mut variables = HashMap::new();
+
+        for export in &env.exports {
+            if let Some(value) = env.get_variable(export) {
+                variables.insert(export.clone(), value);
+            }
+        }
+
+        Module {
+            name,
+            variables,
+            lib_functions: Vec::new(),
+        }
+    }
+
+    pub fn get_lib_function(&self, name: &str) -> Option<&LibFunction> {
+        self.lib_functions.iter().find(|f| f.name == name)
+    }
+}
+
+/// Loads every module at most once per run and keeps track of the files that
+/// are currently being evaluated so that circular imports can be reported
+#[derive(Debug, Default)]
+pub struct ModuleLoader {
+    cache: HashMap<PathBuf, Module>,
+    stack: Vec<PathBuf>,
+    pub errors: Vec<VeloError>,
+}
+
+impl ModuleLoader {
+    pub fn new(entry: Option<&Path>) -> Self {
+        let mut loader = Self::default();
+        if let Some(path) = entry.and_then(|p| fs::canonicalize(p).ok()) {
+            loader.stack.push(path);
+        }
+        loader
+    }
+
+    pub fn load_file(&mut self, path: &str, from: Option<&Path>) -> Result<Module, String> {
+        // Relative paths are resolved against the directory of the importing file
+        let base = from
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let joined = base.join(path);
+        let resolved = fs::canonicalize(&joined)
+            .map_err(|_| format!("Could not find module '{}' ({})", path, joined.display()))?;
+
+        if let Some(start) = self.stack.iter().position(|p| *p == resolved) {
+            let chain: Vec<String> = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&resolved))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(format!("Circular import detected: {}", chain.join(" -> ")));
+        }
+
+        if let Some(module) = self.cache.get(&resolved) {
+            return Ok(module.clone());
+        }
+
+        let contents = fs::read_to_string(&resolved)
+            .map_err(|_| format!("Error reading module: {}", resolved.display()))?;
+        let name = resolved
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        let mut lexer = Lexer::new(&contents);
+        let tokens = lexer.tokenize().tokens;
+        let mut parser = Parser::new(&tokens);
+        // errors inside the module are reported in its file
+        let nodes = match parser.parse() {
+            Ok(nodes) => nodes,
+            Err(errors) => {
+                self.errors
+                    .extend(errors.into_iter().map(|e| e.in_file(&joined)));
+                return Err(format!("Could not parse module '{}'", path));
+            }
+        };
+        if let Err(errors) = check(&nodes, Some(&joined)) {
+            self.errors
+                .extend(errors.into_iter().map(|e| e.in_file(&joined)));
+            return Err(format!("Could not check module '{}'", path));
+        }
+
+        self.stack.push(resolved.clone());
+        let env = run(nodes, Some(&resolved), self);
+        self.stack.pop();
+
+        let mut env = env.borrow_mut();
+        let errors = std::mem::take(&mut env.errors);
+        self.errors
+            .extend(errors.into_iter().map(|e| e.in_file(&joined)));
+        let module = Module::from_env(name, &env);
+        self.cache.insert(resolved, module.clone());
+
+        Ok(module)
+    }
+
+    pub fn load_library(&mut self, path: &str) -> Result<Module, String> {
+        let (variables, lib_functions) = match library(path) {
+            Some(library) => library,
+            None => return Err(format!("Unknown library module '{}'", path)),
+        };
+
+        let name = path.rsplit("::").next().unwrap_or(path).to_string();
+
+        Ok(Module {
+            name,
+            variables: variables
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+            lib_functions,
+        })
+    }
+}
@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::environment::Captured;
+use super::eval::number;
+use crate::syntax::ast::{FunctionBody, Param};
+use crate::syntax::lexer::Type;
+
+/// What evaluating an expression gives, and what variables hold
+#[derive(Debug, Clone)]
+pub enum Value {
+    Short(i16),
+    Int(i32),
+    Large(i64),
+    Float(f32),
+    Bool(bool),
+    String(String),
+    Null,
+    Array(Vec<Value>),
+    /// Keeps its entries in insertion order
+    Map(Vec<(Value, Value)>),
+    /// `start..end step step`, where every bound is a number
+    Range {
+        start: Box<Value>,
+        end: Box<Value>,
+        inclusive: bool,
+        step: Option<Box<Value>>,
+    },
+    /// A function together with the scope it was defined in
+    Function {
+        params: Vec<Param>,
+        body: FunctionBody,
+        ret_type: Type,
+        env: Captured,
+    },
+    /// A function provided by the interpreter used as a value, i.e. `print`
+    /// in `apply(print, 1)`, by its name or `module::member` path
+    Builtin(String),
+}
+
+impl Value {
+    /// Checks whether the value can be stored in a variable of type `ty`.
+    /// Integers are accepted by every integer type they fit in, and by `float`
+    pub fn matches_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (Value::Bool(_), Type::Bool)
+            | (Value::String(_), Type::String)
+            | (Value::Float(_), Type::Float)
+            | (Value::Null, Type::Void)
+            | (Value::Null, Type::Optional(_))
+            | (
+                Value::Function { .. } | Value::Builtin(_),
+                Type::Function { .. } | Type::AnyFunction,
+            )
+            | (Value::Range { .. }, Type::Range) => true,
+            (value, Type::Short | Type::Int | Type::Large | Type::Float) => {
+                match number::integer(value) {
+                    Some(n) => match ty {
+                        Type::Short => i16::try_from(n).is_ok(),
+                        Type::Int => i32::try_from(n).is_ok(),
+                        _ => true,
+                    },
+                    None => false,
+                }
+            }
+            (value, Type::Optional(ty)) => value.matches_type(ty),
+            (Value::Array(items), Type::Array(ty)) => {
+                items.iter().all(|item| item.matches_type(ty))
+            }
+            (Value::Map(entries), Type::Map(key_ty, value_ty)) => entries
+                .iter()
+                .all(|(key, value)| key.matches_type(key_ty) && value.matches_type(value_ty)),
+            _ => false,
+        }
+    }
+
+    /// Like `matches_type`, but a type parameter matches anything the first
+    /// time it's seen and is bound to that value's type in `bindings`
+    pub fn matches_generic(&self, ty: &Type, bindings: &mut HashMap<String, Type>) -> bool {
+        match ty {
+            Type::Param(name) => match bindings.get(name) {
+                Some(bound) => self.matches_type(bound),
+                None => {
+                    if let Some(ty) = self.value_type() {
+                        bindings.insert(name.clone(), ty);
+                    }
+                    true
+                }
+            },
+            Type::Optional(ty) => matches!(self, Value::Null) || self.matches_generic(ty, bindings),
+            Type::Array(ty) => match self {
+                Value::Array(items) => items.iter().all(|item| item.matches_generic(ty, bindings)),
+                _ => false,
+            },
+            Type::Map(key_ty, value_ty) => match self {
+                Value::Map(entries) => entries.iter().all(|(key, value)| {
+                    key.matches_generic(key_ty, bindings)
+                        && value.matches_generic(value_ty, bindings)
+                }),
+                _ => false,
+            },
+            _ => self.matches_type(ty),
+        }
+    }
+
+    /// The type of the value, or `None` for `null` and empty arrays and maps
+    pub fn value_type(&self) -> Option<Type> {
+        let ty = match self {
+            Value::Short(_) => Type::Short,
+            Value::Int(_) => Type::Int,
+            Value::Large(_) => Type::Large,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::String(_) => Type::String,
+            Value::Function {
+                params, ret_type, ..
+            } => Type::Function {
+                params: params.iter().map(Param::binding_type).collect(),
+                ret: Box::new(ret_type.clone()),
+            },
+            // built ins may be variadic or generic, which a function type
+            // can't spell out
+            Value::Builtin(_) => Type::AnyFunction,
+            Value::Array(items) => Type::Array(Box::new(items.first()?.value_type()?)),
+            Value::Range { .. } => Type::Range,
+            Value::Map(entries) => {
+                let (key, value) = entries.first()?;
+                Type::Map(Box::new(key.value_type()?), Box::new(value.value_type()?))
+            }
+            Value::Null => return None,
+        };
+        Some(ty)
+    }
+
+    /// How the type of the value is shown to the user. Arrays and maps that
+    /// are empty or mix types are only called `array` and `map`
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Function { .. } | Value::Builtin(_) => "fun".to_string(),
+            Value::Map(_) => match self.value_type() {
+                Some(ty) if self.matches_type(&ty) => ty.to_string(),
+                _ => "map".to_string(),
+            },
+            Value::Array(_) => match self.value_type() {
+                Some(ty) if self.matches_type(&ty) => ty.to_string(),
+                _ => "array".to_string(),
+            },
+            value => value
+                .value_type()
+                .map(|ty| ty.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// How the value is ordered against `other` by `<` and friends, where
+    /// numbers are ordered by value and strings alphabetically. `None` for
+    /// values that can't be ordered, and for `NaN`
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (lhs, rhs) => number::compare(lhs, rhs),
+        }
+    }
+
+    /// Whether the value counts as true in a condition, or `None` if it can't
+    /// be used as one. Only bools can, `0`, `""` and `null` aren't false
+    pub fn truthy(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+/// Numbers are equal when they have the same value, no matter which types
+/// they're stored as, also inside of arrays and maps. Functions are only equal
+/// when they're the same definition in the same scope, or the same built in
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (
+                Value::Range {
+                    start,
+                    end,
+                    inclusive,
+                    step,
+                },
+                Value::Range {
+                    start: other_start,
+                    end: other_end,
+                    inclusive: other_inclusive,
+                    step: other_step,
+                },
+            ) => {
+                start == other_start
+                    && end == other_end
+                    && inclusive == other_inclusive
+                    && step == other_step
+            }
+            (
+                Value::Function { body, env, .. },
+                Value::Function {
+                    body: other_body,
+                    env: other_env,
+                    ..
+                },
+            ) => body == other_body && env == other_env,
+            (Value::Builtin(a), Value::Builtin(b)) => a == b,
+            (lhs, rhs) => match (number::integer(lhs), number::integer(rhs)) {
+                (Some(a), Some(b)) => a == b,
+                _ => match (number::float(lhs), number::float(rhs)) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => false,
+                },
+            },
+        }
+    }
+}
+
+/// How a value is shown by `print` and `println`, and when cast to a string
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Short(n) => write!(f, "{}", n),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Large(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(string) => write!(f, "{}", string),
+            Value::Null => write!(f, "null"),
+            Value::Function { .. } | Value::Builtin(_) => write!(f, "<function>"),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Value::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                let op = if *inclusive { "..=" } else { ".." };
+                write!(f, "{}{}{}", start, op, end)?;
+                match step {
+                    Some(step) => write!(f, " step {}", step),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 use super::lexer::{TokenType, Type};
+use crate::error::Lint;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Ast {
@@ -8,47 +9,196 @@ pub enum Ast {
     Statement(Statement),
 }
 
+/// Statements and the expressions that can fail a check keep the line they
+/// start on, for reporting errors
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     VariableAssignment {
         constant: bool,
         name: String,
+        ty: Option<Type>,
         value: Expression,
+        line: usize,
     },
     Function {
         name: String,
-        params: Vec<(String, Type)>,
+        type_params: Vec<String>,
+        params: Vec<Param>,
         body: FunctionBody,
         ret_type: Type,
+        line: usize,
+        /// The line of the closing brace of its body
+        end_line: usize,
     },
     ImportPath {
         path: String,
         is_library: bool,
+        line: usize,
+    },
+    Export(Box<Statement>),
+    Reassignment {
+        name: String,
+        value: Expression,
+        line: usize,
+    },
+    /// `name[index] = value`, possibly with several indices, i.e. `m["a"][0] = 1`
+    IndexAssignment {
+        name: String,
+        indices: Vec<Expression>,
+        value: Expression,
+        line: usize,
+    },
+    Return {
+        value: Expression,
+        line: usize,
+    },
+    /// `for name in iterable { body }`, or `for key, value in iterable { body }`
+    /// to also get the index or key of each item
+    For {
+        name: String,
+        value_name: Option<String>,
+        iterable: Expression,
+        body: FunctionBody,
+        line: usize,
+    },
+    /// `loop { body }`, running its body until a `break` or `return`
+    Loop {
+        body: FunctionBody,
+        line: usize,
+    },
+    /// `break`, leaving the innermost `for` or `loop`
+    Break {
+        line: usize,
+    },
+    /// `continue`, going on with the next round of the innermost `for` or
+    /// `loop`
+    Continue {
+        line: usize,
     },
     ExprStmt(Expression),
+    /// A statement after an attribute like `@allow(unused_variables)`, which
+    /// only applies to that statement
+    Attributed {
+        attribute: Attribute,
+        stmt: Box<Statement>,
+    },
 }
 
+impl Statement {
+    pub fn declared_name(&self) -> Option<&str> {
+        match self {
+            Statement::VariableAssignment { name, .. } | Statement::Function { name, .. } => {
+                Some(name)
+            }
+            Statement::Export(stmt) | Statement::Attributed { stmt, .. } => stmt.declared_name(),
+            _ => None,
+        }
+    }
+
+    /// Whether the attributes before the statement turn off warnings of `lint`
+    pub fn allows(&self, lint: Lint) -> bool {
+        match self {
+            Statement::Attributed { attribute, stmt } => {
+                attribute.allowed().contains(&lint) || stmt.allows(lint)
+            }
+            _ => false,
+        }
+    }
+
+    /// The statement without the attributes before it
+    pub fn unattributed(&self) -> &Statement {
+        match self {
+            Statement::Attributed { stmt, .. } => stmt.unattributed(),
+            stmt => stmt,
+        }
+    }
+}
+
+/// An attribute like `@allow(unused_variables)` before a statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Attribute {
+    /// The lints this turns off
+    pub fn allowed(&self) -> Vec<Lint> {
+        match self.name.as_str() {
+            "allow" => self
+                .args
+                .iter()
+                .filter_map(|arg| Lint::from_name(arg))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The statements of a function or block, optionally followed by a trailing
+/// expression that it evaluates to, i.e. `{ x := 1; x * 2 }`
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionBody {
-    stmts: Vec<Statement>,
-    exprs: Vec<Expression>,
-    block: Option<Box<FunctionBody>>,
+    pub stmts: Vec<Statement>,
+    pub value: Option<Box<Expression>>,
 }
 
 impl FunctionBody {
-    pub fn new(
-        stmts: Vec<Statement>,
-        exprs: Vec<Expression>,
-        block: Option<Box<FunctionBody>>,
-    ) -> FunctionBody {
-        FunctionBody {
-            stmts,
-            exprs,
-            block,
+    pub fn new(stmts: Vec<Statement>, value: Option<Box<Expression>>) -> FunctionBody {
+        FunctionBody { stmts, value }
+    }
+}
+
+/// A function parameter, i.e. `name string` or `greeting string = "hi"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+    /// Evaluated at each call that doesn't pass this parameter, after the
+    /// parameters before it have been bound
+    pub default: Option<Expression>,
+    /// Whether this is a `nums ...int` parameter, collecting every remaining
+    /// positional argument into an array
+    pub variadic: bool,
+}
+
+impl Param {
+    /// The type of the variable the parameter is bound to, which for variadic
+    /// parameters is an array of `ty`
+    pub fn binding_type(&self) -> Type {
+        match self.variadic {
+            true => Type::Array(Box::new(self.ty.clone())),
+            false => self.ty.clone(),
         }
     }
 }
 
+/// An argument in a call, either positional, named (`name: "Al"`) or an array
+/// spread into positional arguments (`...xs`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub name: Option<String>,
+    pub value: Expression,
+    pub spread: bool,
+}
+
+impl Expression {
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Expression::Short(_)
+                | Expression::Int(_)
+                | Expression::Large(_)
+                | Expression::Float(_)
+                | Expression::Bool(_)
+                | Expression::StringLiteral(_)
+                | Expression::Null
+                | Expression::Lambda { .. }
+        ) || matches!(self, Expression::Array(items) if items.iter().all(Expression::is_literal))
+            || matches!(self, Expression::Map(entries) if entries.iter().all(|(k, v)| k.is_literal() && v.is_literal()))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Short(i16),
@@ -59,15 +209,80 @@ pub enum Expression {
     StringLiteral(String),
     Identifier(String),
     Null,
+    Array(Vec<Expression>),
+    /// `{"a": 1, "b": 2}`, keeping its entries in insertion order
+    Map(Vec<(Expression, Expression)>),
 
     CallExpr {
         name: String,
-        params: Vec<Expression>,
+        params: Vec<Argument>,
+        line: usize,
+    },
+
+    Lambda {
+        params: Vec<Param>,
+        body: FunctionBody,
+        ret_type: Type,
     },
 
     BinaryOp {
         lhs: Box<Ast>,
         op: TokenType,
         rhs: Box<Ast>,
+        line: usize,
+    },
+
+    UnaryOp {
+        op: TokenType,
+        expr: Box<Expression>,
+    },
+
+    /// `start..end`, `start..=end` or `start..end step n`
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        inclusive: bool,
+        step: Option<Box<Expression>>,
+    },
+    /// `target[index]`, where `index` is either a number or a range. Safe
+    /// indexing, i.e. `target?.[index]` or `target?.key`, gives `null` instead
+    /// when the target is `null`
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+        safe: bool,
+        line: usize,
+    },
+    /// `callee(args)` where `callee` is any expression other than a name,
+    /// i.e. `adder(2)(3)` or `fs[0](4)`
+    Call {
+        callee: Box<Expression>,
+        params: Vec<Argument>,
+        line: usize,
+    },
+    /// `value as ty`, converting between numeric types and to and from strings
+    Cast {
+        value: Box<Expression>,
+        ty: Type,
+        line: usize,
+    },
+
+    /// `{ stmt; stmt; value }`, evaluating to its trailing expression
+    Block(FunctionBody),
+    /// `if cond { } else { }`, where `else_branch` is either a block or another
+    /// `if` for `elif` and `else if`
+    If {
+        cond: Box<Expression>,
+        then_branch: FunctionBody,
+        else_branch: Option<Box<Expression>>,
+        line: usize,
+    },
+    /// `try { } catch e { }`, evaluating to the value of the `try` block, or
+    /// to that of the `catch` block when an error is raised inside it. The
+    /// error's message is bound to `error_name`, which can be left out
+    Try {
+        body: FunctionBody,
+        error_name: Option<String>,
+        handler: FunctionBody,
     },
 }
@@ -1,5 +1,7 @@
-use crate::error::ERROR_INDICATOR;
+use std::collections::HashMap;
+use std::fmt;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
     // Literal Types
@@ -16,10 +18,14 @@ pub enum TokenType {
     ElseIf,
 
     For,
+    In,
+    As,
     Loop,
     Break,
     Continue,
     Return,
+    Try,
+    Catch,
 
     True,
     False,
@@ -38,6 +44,7 @@ pub enum TokenType {
     Mod,
     Not,
     Dollar,
+    At,
     LParen,
     RParen,
     LBracket,
@@ -48,12 +55,19 @@ pub enum TokenType {
     Comma,
     Semicolon,
     Dot,
+    DotDot,
+    DotDotEq,
+    Ellipsis,
+    Question,
+    QuestionDot,
+    QuestionQuestion,
     Colon,
     DoubleColon,
     ColonEq,
     Arrow,
     Tilde,
     BitwiseOr,
+    BitwiseXor,
     BitwiseAnd,
     ShiftLeft,
     ShiftRight,
@@ -81,10 +95,41 @@ impl TokenType {
         let string_repr = format!("{:#?}", token_type);
         format!("{}{}", &string_repr[..1].to_lowercase(), &string_repr[1..])
     }
+
+    /// How an operator is written in source, for error messages
+    pub fn symbol(self) -> String {
+        let symbol = match self {
+            TokenType::Add => "+",
+            TokenType::Sub => "-",
+            TokenType::Mul => "*",
+            TokenType::Div => "/",
+            TokenType::Mod => "%",
+            TokenType::Not => "!",
+            TokenType::Tilde => "~",
+            TokenType::BitwiseOr => "|",
+            TokenType::BitwiseXor => "^",
+            TokenType::BitwiseAnd => "&",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
+            TokenType::And => "&&",
+            TokenType::Or => "||",
+            TokenType::EqEq => "==",
+            TokenType::Ne => "!=",
+            TokenType::Lt => "<",
+            TokenType::Gt => ">",
+            TokenType::LtEq => "<=",
+            TokenType::GtEq => ">=",
+            TokenType::In => "in",
+            TokenType::As => "as",
+            TokenType::QuestionQuestion => "??",
+            _ => return TokenType::to_string(self),
+        };
+        symbol.to_string()
+    }
 }
 
 pub struct KeywordMap {
-    data: [(&'static str, TokenType); 18],
+    data: [(&'static str, TokenType); 22],
 }
 
 impl KeywordMap {
@@ -97,10 +142,14 @@ impl KeywordMap {
                 ("else", TokenType::Else),
                 ("elif", TokenType::ElseIf),
                 ("for", TokenType::For),
+                ("in", TokenType::In),
+                ("as", TokenType::As),
                 ("loop", TokenType::Loop),
                 ("break", TokenType::Break),
                 ("continue", TokenType::Continue),
                 ("return", TokenType::Return),
+                ("try", TokenType::Try),
+                ("catch", TokenType::Catch),
                 ("true", TokenType::True),
                 ("false", TokenType::False),
                 ("import", TokenType::Import),
@@ -115,7 +164,7 @@ impl KeywordMap {
     pub fn get(&self, key: &str) -> Option<TokenType> {
         for (k, v) in &self.data {
             if *k == key {
-                return Some(v.clone());
+                return Some(*v);
             }
         }
         None
@@ -141,13 +190,39 @@ pub enum Type {
     String,
     Array(Box<Type>),
     Tuple(Vec<Type>),
+    Function {
+        params: Vec<Type>,
+        ret: Box<Type>,
+    },
+    /// A bare `fun`, which any function can be given to
+    AnyFunction,
+    Range,
+    /// `map<K, V>`
+    Map(Box<Type>, Box<Type>),
+    /// `T?`, a value of type `T` or `null`
+    Optional(Box<Type>),
+    /// A type parameter of a generic function, e.g. the `T` in `fun id<T>(x T) > T`
+    Param(String),
+    /// Any value at all, only used in the signatures of built in functions
+    Any,
     Void,
 }
 
 impl Type {
-    pub fn from_string(string: String) -> Type {
-        let type_str = &string;
-        let type_result = match type_str.as_str() {
+    pub fn is_optional(&self) -> bool {
+        matches!(self, Type::Optional(_))
+    }
+
+    /// The type of the value of an optional once it's known not to be `null`
+    pub fn non_optional(&self) -> &Type {
+        match self {
+            Type::Optional(ty) => ty,
+            ty => ty,
+        }
+    }
+
+    pub fn from_string(string: String) -> Option<Type> {
+        match string.as_str() {
             "bool" => Some(Type::Bool),
             "int" => Some(Type::Int),
             "short" => Some(Type::Short),
@@ -156,18 +231,79 @@ impl Type {
             "string" => Some(Type::String),
             "array" => Some(Type::Array(Box::new(Type::Bool))),
             "tuple" => Some(Type::Tuple(vec![Type::Bool])),
+            "fun" => Some(Type::AnyFunction),
+            "range" => Some(Type::Range),
             "void" => Some(Type::Void),
             _ => None,
-        };
+        }
+    }
+
+    /// Whether the type still refers to type parameters that weren't bound
+    pub fn contains_param(&self) -> bool {
+        match self {
+            Type::Param(_) => true,
+            Type::Array(ty) | Type::Optional(ty) => ty.contains_param(),
+            Type::Map(key, value) => key.contains_param() || value.contains_param(),
+            Type::Tuple(types) => types.iter().any(Type::contains_param),
+            Type::Function { params, ret } => {
+                params.iter().any(Type::contains_param) || ret.contains_param()
+            }
+            _ => false,
+        }
+    }
 
-        if type_result.is_none() {
-            let message = format!("{} Unknown type '{}'", ERROR_INDICATOR, type_str);
-            println!("{}", message);
-            // Todo: implement error handling kinda like parser
-            std::process::exit(1);
+    /// Replaces the type parameters in this type with the types they were bound
+    /// to at a call site, leaving unbound ones as they are
+    pub fn instantiate(&self, bindings: &HashMap<String, Type>) -> Type {
+        match self {
+            Type::Param(name) => bindings.get(name).cloned().unwrap_or(self.clone()),
+            Type::Array(ty) => Type::Array(Box::new(ty.instantiate(bindings))),
+            Type::Optional(ty) => Type::Optional(Box::new(ty.instantiate(bindings))),
+            Type::Map(key, value) => Type::Map(
+                Box::new(key.instantiate(bindings)),
+                Box::new(value.instantiate(bindings)),
+            ),
+            Type::Tuple(types) => {
+                Type::Tuple(types.iter().map(|t| t.instantiate(bindings)).collect())
+            }
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|t| t.instantiate(bindings)).collect(),
+                ret: Box::new(ret.instantiate(bindings)),
+            },
+            _ => self.clone(),
         }
+    }
+}
 
-        type_result.unwrap()
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Int => write!(f, "int"),
+            Type::Short => write!(f, "short"),
+            Type::Large => write!(f, "large"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Array(ty) => write!(f, "[{}]", ty),
+            Type::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", types.join(", "))
+            }
+            Type::AnyFunction => write!(f, "fun"),
+            Type::Function { params, ret } => {
+                let params: Vec<String> = params.iter().map(|t| t.to_string()).collect();
+                write!(f, "fun({}) -> {}", params.join(", "), ret)
+            }
+            Type::Param(name) => write!(f, "{}", name),
+            Type::Optional(ty) if matches!(**ty, Type::Function { .. } | Type::AnyFunction) => {
+                write!(f, "({})?", ty)
+            }
+            Type::Optional(ty) => write!(f, "{}?", ty),
+            Type::Range => write!(f, "range"),
+            Type::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Type::Any => write!(f, "any"),
+            Type::Void => write!(f, "void"),
+        }
     }
 }
 
@@ -192,7 +328,7 @@ impl Lexer {
         let mut string = String::new();
         self.source.remove(0);
 
-        while let Some(c) = self.source.get(0) {
+        while let Some(c) = self.source.first() {
             match c {
                 '"' => {
                     self.source.remove(0);
@@ -256,7 +392,7 @@ impl Lexer {
                 },
                 '-' => match self.source[1] {
                     '=' => tokens.push(self.make_long_token("-=", TokenType::MinusEq)),
-                    '>' => tokens.push(self.make_long_token("-=", TokenType::Arrow)),
+                    '>' => tokens.push(self.make_long_token("->", TokenType::Arrow)),
                     _ => tokens.push(self.make_token(self.source[0], TokenType::Sub)),
                 },
                 '*' => match self.source[1] {
@@ -280,10 +416,29 @@ impl Lexer {
 
                 '%' => tokens.push(self.make_token(self.source[0], TokenType::Mod)),
                 '$' => tokens.push(self.make_token(self.source[0], TokenType::Dollar)),
+                '@' => tokens.push(self.make_token(self.source[0], TokenType::At)),
 
                 ',' => tokens.push(self.make_token(self.source[0], TokenType::Comma)),
                 ';' => tokens.push(self.make_token(self.source[0], TokenType::Semicolon)),
-                '.' => tokens.push(self.make_token(self.source[0], TokenType::Dot)),
+                '.' => match (self.source.get(1), self.source.get(2)) {
+                    (Some('.'), Some('.')) => {
+                        self.source.remove(0);
+                        tokens.push(self.make_long_token("...", TokenType::Ellipsis))
+                    }
+                    (Some('.'), Some('=')) => {
+                        self.source.remove(0);
+                        tokens.push(self.make_long_token("..=", TokenType::DotDotEq))
+                    }
+                    (Some('.'), _) => tokens.push(self.make_long_token("..", TokenType::DotDot)),
+                    _ => tokens.push(self.make_token(self.source[0], TokenType::Dot)),
+                },
+                '?' => match self.source.get(1) {
+                    Some('.') => tokens.push(self.make_long_token("?.", TokenType::QuestionDot)),
+                    Some('?') => {
+                        tokens.push(self.make_long_token("??", TokenType::QuestionQuestion))
+                    }
+                    _ => tokens.push(self.make_token(self.source[0], TokenType::Question)),
+                },
                 ':' => match self.source[1] {
                     ':' => tokens.push(self.make_long_token("::", TokenType::DoubleColon)),
                     '=' => tokens.push(self.make_long_token(":=", TokenType::ColonEq)),
@@ -296,6 +451,8 @@ impl Lexer {
                     _ => tokens.push(self.make_token(self.source[0], TokenType::BitwiseOr)),
                 },
 
+                '^' => tokens.push(self.make_token(self.source[0], TokenType::BitwiseXor)),
+
                 '&' => match self.source[1] {
                     '&' => tokens.push(self.make_long_token("&&", TokenType::And)),
                     _ => tokens.push(self.make_token(self.source[0], TokenType::BitwiseAnd)),
@@ -325,9 +482,11 @@ impl Lexer {
                                 self.source.remove(0);
                             }
                         }
-                    } else if self.source[0].is_ascii_alphabetic() {
+                    } else if self.source[0].is_ascii_alphabetic() || self.source[0] == '_' {
                         let mut ident = String::new();
-                        while !self.source.is_empty() && self.source[0].is_ascii_alphabetic() {
+                        while !self.source.is_empty()
+                            && (self.source[0].is_ascii_alphanumeric() || self.source[0] == '_')
+                        {
                             ident.push(self.source.remove(0));
                         }
                         match KEYWORDS.get(&ident[..]) {
@@ -342,10 +501,12 @@ impl Lexer {
                                 lexeme: ident,
                             }),
                         }
-                    } else if self.source[0].is_digit(10) {
+                    } else if self.source[0].is_ascii_digit() {
                         let mut num = String::new();
+                        // a '.' followed by another one starts a range, i.e. `1..5`
                         while !self.source.is_empty()
-                            && (self.source[0].is_digit(10) || self.source[0] == '.')
+                            && (self.source[0].is_ascii_digit()
+                                || (self.source[0] == '.' && self.source.get(1) != Some(&'.')))
                         {
                             num.push(self.source.remove(0));
                         }
@@ -0,0 +1,184 @@
+use super::super::Parser;
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::ast::{Ast, Expression};
+use crate::syntax::lexer::{TokenType, KEYWORDS};
+
+impl Parser<'_> {
+    /// Parses the expression starting at the cursor, leaving the cursor after it
+    pub fn parse_value(&mut self) -> Expression {
+        let value = self.parse_binary(0);
+
+        // expressions ending in a block, like `if`, don't need a `;`
+        let token = self.peek();
+        let after_block =
+            self.cursor > 0 && self.tokens[self.cursor - 1].token_type == TokenType::RBrace;
+        if KEYWORDS.get(&token.lexeme).is_some() && !after_block {
+            let message = format!(
+                "{} \x1b[1mExpected ';' after expression, found keyword '{}'\x1b[0m",
+                ERROR_INDICATOR,
+                TokenType::to_string(token.token_type),
+            );
+            self.throw_error(token.line_num, message);
+        }
+
+        value
+    }
+
+    /// Precedence climbing over the binary operators, only consuming operators
+    /// that bind at least as tightly as `min_precedence`
+    pub fn parse_binary(&mut self, min_precedence: u8) -> Expression {
+        let mut lhs = self.parse_unary();
+
+        loop {
+            let token = self.peek();
+            let op = token.token_type;
+
+            // `as` binds tighter than every binary operator, and is followed by
+            // a type rather than an expression
+            if op == TokenType::As {
+                self.advance();
+                lhs = Expression::Cast {
+                    value: Box::new(lhs),
+                    ty: self.parse_type(),
+                    line: token.line_num,
+                };
+                continue;
+            }
+
+            let precedence = match Self::precedence(op) {
+                Some(precedence) if precedence >= min_precedence => precedence,
+                _ => break,
+            };
+            self.advance();
+
+            // every operator is left associative, so the right hand side may
+            // only contain operators that bind tighter
+            let rhs = self.parse_binary(precedence + 1);
+            lhs = match op {
+                TokenType::DotDot | TokenType::DotDotEq => {
+                    self.parse_range_step(lhs, rhs, op == TokenType::DotDotEq, precedence)
+                }
+                _ => Expression::BinaryOp {
+                    lhs: Box::new(Ast::Expression(lhs)),
+                    op,
+                    rhs: Box::new(Ast::Expression(rhs)),
+                    line: token.line_num,
+                },
+            };
+        }
+
+        lhs
+    }
+
+    /// Finishes a range after its end, reading the optional `step n`
+    fn parse_range_step(
+        &mut self,
+        start: Expression,
+        end: Expression,
+        inclusive: bool,
+        precedence: u8,
+    ) -> Expression {
+        // `step` is only special right after a range, so it stays usable as a name
+        let step = match self.peek() {
+            token if token.token_type == TokenType::Identifier && token.lexeme == "step" => {
+                self.advance();
+                Some(Box::new(self.parse_binary(precedence + 1)))
+            }
+            _ => None,
+        };
+
+        Expression::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+            step,
+        }
+    }
+
+    fn parse_unary(&mut self) -> Expression {
+        match self.peek().token_type {
+            op @ (TokenType::Sub | TokenType::Not | TokenType::Tilde) => {
+                self.advance();
+                let expr = self.parse_unary();
+                Expression::UnaryOp {
+                    op,
+                    expr: Box::new(expr),
+                }
+            }
+            _ => self.parse_postfix(),
+        }
+    }
+
+    /// Parses any number of `[index]`, `?.[index]`, `?.key` and `(args)` after
+    /// a primary expression
+    fn parse_postfix(&mut self) -> Expression {
+        let mut expr = self.parse_primary();
+
+        loop {
+            let line = self.peek().line_num;
+            let safe = match self.peek().token_type {
+                TokenType::LBracket => false,
+                TokenType::QuestionDot => true,
+                TokenType::LParen => {
+                    self.advance();
+                    let params = self.parse_args("this function");
+                    expr = Expression::Call {
+                        callee: Box::new(expr),
+                        params,
+                        line,
+                    };
+                    continue;
+                }
+                _ => break,
+            };
+            self.advance();
+
+            // `m?.key` is short for `m?.["key"]`
+            let index = match self.peek() {
+                token if safe && token.token_type == TokenType::Identifier => {
+                    self.advance();
+                    Expression::StringLiteral(token.lexeme.clone())
+                }
+                _ => {
+                    if safe
+                        && self
+                            .expect(TokenType::LBracket, "'[' or a key after '?.'")
+                            .is_none()
+                    {
+                        return Expression::Null;
+                    }
+                    let index = self.parse_binary(0);
+                    self.expect(TokenType::RBracket, "']' to close index");
+                    index
+                }
+            };
+            expr = Expression::Index {
+                target: Box::new(expr),
+                index: Box::new(index),
+                safe,
+                line,
+            };
+        }
+
+        expr
+    }
+
+    fn precedence(op: TokenType) -> Option<u8> {
+        let precedence = match op {
+            TokenType::QuestionQuestion => 1,
+            TokenType::Or => 2,
+            TokenType::And => 3,
+            TokenType::EqEq | TokenType::Ne => 4,
+            TokenType::Lt | TokenType::Gt | TokenType::LtEq | TokenType::GtEq | TokenType::In => 5,
+            TokenType::DotDot | TokenType::DotDotEq => 6,
+            TokenType::BitwiseOr => 7,
+            TokenType::BitwiseXor => 8,
+            TokenType::BitwiseAnd => 9,
+            TokenType::ShiftLeft | TokenType::ShiftRight => 10,
+            TokenType::Add | TokenType::Sub => 11,
+            TokenType::Mul | TokenType::Div | TokenType::Mod => 12,
+            _ => return None,
+        };
+        Some(precedence)
+    }
+}
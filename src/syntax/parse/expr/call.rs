@@ -1,130 +1,91 @@
 use super::super::Parser;
-use crate::syntax::ast::{Ast, Expression};
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::ast::{Argument, Expression};
 use crate::syntax::lexer::TokenType;
 
-impl Parser {
-    pub fn call_expr(&mut self) -> Expression {
-        let name = self.tokens[self.cursor].lexeme.clone();
-        self.cursor += 2;
+impl Parser<'_> {
+    /// Parses `name(args)` starting at the cursor, leaving the cursor after the
+    /// closing paren. Every argument may be an arbitrary expression, optionally
+    /// named, i.e. `greet("Al", greeting: "hey")`, or spread, i.e. `sum(...xs)`
+    pub fn parse_call(&mut self) -> Expression {
+        let line = self.peek().line_num;
+        let name = self.qualified_name();
+        self.advance();
 
+        let params = self.parse_args(&format!("'{}'", name));
+        Expression::CallExpr { name, params, line }
+    }
+
+    /// Parses the arguments of a call after its opening paren, leaving the
+    /// cursor after the closing one. `callee` names the function in errors
+    pub fn parse_args(&mut self, callee: &str) -> Vec<Argument> {
         let mut params = Vec::new();
 
-        while let Some(param_token) = self.tokens.get(self.cursor) {
-            match param_token.token_type {
-                TokenType::String => {
-                    params.push(Expression::StringLiteral(param_token.lexeme.clone()));
-                    self.cursor += 1;
+        if self.check(TokenType::RParen) {
+            self.advance();
+            return params;
+        }
 
-                    if let Some(next_token) = self.tokens.get(self.cursor) {
-                        match next_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    } else {
-                        std::process::exit(1)
+        while !self.is_at_end() {
+            let token = self.peek();
+            let named = token.token_type == TokenType::Identifier
+                && self.peek_at(1).token_type == TokenType::Colon;
+
+            let argument = match (named, token.token_type) {
+                (true, _) => {
+                    self.advance();
+                    self.advance();
+                    Argument {
+                        name: Some(token.lexeme.clone()),
+                        value: self.parse_binary(0),
+                        spread: false,
                     }
                 }
-                TokenType::Identifier => {
-                    let literal = self.parse_literal(param_token.clone(), Some(self.cursor));
-                    self.cursor += 1;
-
-                    params.push(literal);
-                    if let Some(after_token) = self.tokens.get(self.cursor) {
-                        match after_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
+                (false, TokenType::Ellipsis) => {
+                    self.advance();
+                    Argument {
+                        name: None,
+                        value: self.parse_binary(0),
+                        spread: true,
                     }
                 }
-                TokenType::RParen => break,
+                (false, _) => Argument {
+                    name: None,
+                    value: self.parse_binary(0),
+                    spread: false,
+                },
+            };
 
-                _ => {
-                    println!("`{}` is unimplemented!", param_token.lexeme.clone());
-                    unimplemented!()
-                }
+            let after_named = params.last().is_some_and(|a: &Argument| a.name.is_some());
+            if after_named && argument.name.is_none() {
+                let message = format!(
+                    "{} \x1b[1mPositional arguments to {} must come before named ones\x1b[0m",
+                    ERROR_INDICATOR, callee
+                );
+                self.throw_error(token.line_num, message);
             }
-        }
-        let temp = params.clone();
-        let nm = format!("{}", &name);
-        let call_expr = Expression::CallExpr { name, params };
-
-        self.tokens.drain(0..self.cursor); // so uhh... forgot to add this line...
-                                           // took 2 hours to figure out why it wasnt working
-                                           // having fun :)
-
-        self.cursor = 0; // once again, forgot to add this line
-                         // took me around 30 minutes before i walked away
-                         // literally figured out the error while rock climbing... lol
-        self.nodes.push(Ast::Expression(call_expr));
-        Expression::CallExpr {
-            name: nm,
-            params: temp,
-        }
-    }
-
-    pub fn call_expr_as_var(&mut self) -> Expression {
-        let name = self.tokens[self.cursor].lexeme.clone();
-        self.cursor += 2;
-        let mut params = Vec::new();
-
-        while let Some(param_token) = self.tokens.get(self.cursor) {
-            match param_token.token_type {
-                TokenType::String => {
-                    params.push(Expression::StringLiteral(param_token.lexeme.clone()));
-                    self.cursor += 1;
+            params.push(argument);
 
-                    if let Some(next_token) = self.tokens.get(self.cursor) {
-                        match next_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    } else {
-                        std::process::exit(1)
-                    }
+            let token = self.peek();
+            match token.token_type {
+                TokenType::Comma => {
+                    self.advance();
                 }
-                TokenType::Identifier => {
-                    let literal = self.parse_literal(param_token.clone(), Some(self.cursor));
-                    self.cursor += 1;
-
-                    params.push(literal);
-                    if let Some(after_token) = self.tokens.get(self.cursor) {
-                        match after_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    }
+                TokenType::RParen => {
+                    self.advance();
+                    break;
                 }
-                TokenType::RParen => break,
                 _ => {
-                    println!("`{}` is unimplemented!", param_token.lexeme.clone());
-                    unimplemented!()
+                    let message = format!(
+                        "{} \x1b[1mExpected ',' or ')' in call to {}, found '{}'\x1b[0m",
+                        ERROR_INDICATOR, callee, token.lexeme
+                    );
+                    self.throw_error(token.line_num, message);
+                    break;
                 }
             }
         }
-        let call_expr = Expression::CallExpr { name, params };
-
-        self.tokens.drain(0..self.cursor); // so uhh... forgot to add this line...
-                                           // took 2 hours to figure out why it wasnt working
-                                           // having fun :)
-
-        self.cursor = 0;
 
-        call_expr
+        params
     }
 }
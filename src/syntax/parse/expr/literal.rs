@@ -0,0 +1,247 @@
+use super::super::Parser;
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::ast::Expression;
+use crate::syntax::lexer::TokenType;
+
+impl Parser<'_> {
+    /// Parses a literal, array, map, variable, call, lambda, block, `if`, `try`
+    /// or parenthesised expression
+    pub fn parse_primary(&mut self) -> Expression {
+        let token = self.peek();
+
+        match token.token_type {
+            TokenType::Function => return self.parse_lambda(),
+            TokenType::Identifier if self.is_call() => return self.parse_call(),
+            TokenType::LParen => {
+                self.advance();
+                let errors = self.errors.len();
+                let expr = self.parse_binary(0);
+                if self.check(TokenType::RParen) {
+                    self.advance();
+                } else if self.errors.len() == errors {
+                    self.expect(TokenType::RParen, "')' to close expression");
+                }
+                return expr;
+            }
+            TokenType::LBracket => return self.parse_array(),
+            TokenType::LBrace if self.is_map_literal() => return self.parse_map(),
+            TokenType::LBrace => {
+                return match self.parse_block("block") {
+                    Some(body) => Expression::Block(body),
+                    None => Expression::Null,
+                }
+            }
+            TokenType::If => return self.parse_if(),
+            TokenType::Try => return self.parse_try(),
+            _ => {}
+        }
+
+        let literal = match token.token_type {
+            TokenType::True => Expression::Bool(true),
+            TokenType::False => Expression::Bool(false),
+            TokenType::Null => Expression::Null,
+            TokenType::String => Expression::StringLiteral(token.lexeme.clone()),
+            TokenType::Identifier => return Expression::Identifier(self.qualified_name()),
+            TokenType::NumericLiteral => {
+                // whole numbers are ints, or larges when they don't fit in one
+                let number = match token.lexeme.contains('.') {
+                    true => token.lexeme.parse::<f32>().ok().map(Expression::Float),
+                    false => token
+                        .lexeme
+                        .parse::<i64>()
+                        .ok()
+                        .map(|n| match i32::try_from(n) {
+                            Ok(n) => Expression::Int(n),
+                            Err(_) => Expression::Large(n),
+                        }),
+                };
+                match number {
+                    Some(number) => number,
+                    None => {
+                        let message = format!(
+                            "{} \x1b[1mInvalid number literal '{}'\x1b[0m",
+                            ERROR_INDICATOR, token.lexeme
+                        );
+                        self.throw_error(token.line_num, message);
+                        Expression::Null
+                    }
+                }
+            }
+            _ => {
+                let message = format!(
+                    "{} \x1b[1mExpected expression, found '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+
+                // leave delimiters for the enclosing statement or call to handle
+                if !matches!(
+                    token.token_type,
+                    TokenType::Semicolon
+                        | TokenType::RBrace
+                        | TokenType::RParen
+                        | TokenType::Comma
+                        | TokenType::EOF
+                ) {
+                    self.advance();
+                }
+                return Expression::Null;
+            }
+        };
+        self.advance();
+
+        literal
+    }
+
+    /// Parses `[a, b, c]` with the cursor on the opening bracket
+    fn parse_array(&mut self) -> Expression {
+        self.advance();
+        let mut items = Vec::new();
+
+        while !self.check(TokenType::RBracket) {
+            let errors = self.errors.len();
+            items.push(self.parse_binary(0));
+            if self.errors.len() > errors {
+                return Expression::Array(items);
+            }
+
+            if !self.check(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        self.expect(TokenType::RBracket, "',' or ']' in array");
+        Expression::Array(items)
+    }
+
+    /// Parses `if cond { } elif cond { } else { }` with the cursor on `if`
+    fn parse_if(&mut self) -> Expression {
+        let line = self.advance().line_num;
+        let cond = self.parse_binary(0);
+        let then_branch = match self.parse_block("'if' body") {
+            Some(body) => body,
+            None => return Expression::Null,
+        };
+
+        let else_branch = match (self.peek().token_type, self.peek_at(1).token_type) {
+            (TokenType::ElseIf, _) => Some(self.parse_if()),
+            (TokenType::Else, TokenType::If) => {
+                self.advance();
+                Some(self.parse_if())
+            }
+            (TokenType::Else, _) => {
+                self.advance();
+                match self.parse_block("'else' body") {
+                    Some(body) => Some(Expression::Block(body)),
+                    None => return Expression::Null,
+                }
+            }
+            _ => None,
+        };
+
+        Expression::If {
+            cond: Box::new(cond),
+            then_branch,
+            else_branch: else_branch.map(Box::new),
+            line,
+        }
+    }
+
+    /// Parses `try { } catch e { }` with the cursor on `try`
+    fn parse_try(&mut self) -> Expression {
+        self.advance();
+        let body = match self.parse_block("'try' body") {
+            Some(body) => body,
+            None => return Expression::Null,
+        };
+
+        if self
+            .expect(TokenType::Catch, "'catch' after 'try' body")
+            .is_none()
+        {
+            return Expression::Null;
+        }
+        let error_name = match self.peek() {
+            token if token.token_type == TokenType::Identifier => {
+                self.advance();
+                Some(token.lexeme.clone())
+            }
+            _ => None,
+        };
+        let handler = match self.parse_block("'catch' body") {
+            Some(handler) => handler,
+            None => return Expression::Null,
+        };
+
+        Expression::Try {
+            body,
+            error_name,
+            handler,
+        }
+    }
+
+    /// Looks ahead from a `{` to tell a map literal from a block: a map has a
+    /// `:` before the first `;` or `:=`, and `{}` is an empty map. A block can
+    /// start with a `name: type := value` declaration, which also has a `:`
+    pub fn is_map_literal(&mut self) -> bool {
+        let mut depth = 0;
+        let mut offset = 1;
+        loop {
+            match self.peek_at(offset).token_type {
+                TokenType::Immut if offset == 1 => return false,
+                TokenType::Colon if depth == 0 && offset == 2 => {
+                    return self.peek_at(1).token_type != TokenType::Identifier
+                        || !self.is_typed_declaration(offset + 1)
+                }
+                TokenType::LParen | TokenType::LBracket | TokenType::LBrace => depth += 1,
+                TokenType::RParen | TokenType::RBracket => depth -= 1,
+                TokenType::RBrace if depth == 0 => return offset == 1,
+                TokenType::RBrace => depth -= 1,
+                TokenType::Colon if depth == 0 => return true,
+                TokenType::Semicolon | TokenType::ColonEq if depth == 0 => return false,
+                TokenType::EOF => return false,
+                _ => {}
+            }
+            offset += 1;
+        }
+    }
+
+    /// Whether the tokens `offset` ahead of the cursor are a type followed by
+    /// `:=`. Leaves the cursor where it was
+    fn is_typed_declaration(&mut self, offset: usize) -> bool {
+        let (cursor, errors, pending_gt) = (self.cursor, self.errors.len(), self.pending_gt);
+        self.cursor += offset;
+        self.parse_type();
+        let declaration = self.check(TokenType::ColonEq);
+        self.cursor = cursor;
+        self.errors.truncate(errors);
+        self.pending_gt = pending_gt;
+        declaration
+    }
+
+    /// Parses `{key: value, key: value}` with the cursor on the opening brace
+    fn parse_map(&mut self) -> Expression {
+        self.advance();
+        let mut entries = Vec::new();
+
+        while !self.check(TokenType::RBrace) {
+            let errors = self.errors.len();
+            let key = self.parse_binary(0);
+            self.expect(TokenType::Colon, "':' after map key");
+            let value = self.parse_binary(0);
+            if self.errors.len() > errors {
+                return Expression::Map(entries);
+            }
+            entries.push((key, value));
+
+            if !self.check(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        self.expect(TokenType::RBrace, "',' or '}' in map");
+        Expression::Map(entries)
+    }
+}
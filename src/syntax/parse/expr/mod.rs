@@ -1 +1,3 @@
-pub mod call;
\ No newline at end of file
+pub mod binary;
+pub mod call;
+pub mod literal;
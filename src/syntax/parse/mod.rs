@@ -1,248 +1,613 @@
 mod expr;
-#[allow(unused)]
 mod stmt;
+mod types;
+
+use std::collections::HashMap;
 
-use super::ast::Expression;
 use super::ast::*;
-use super::lexer::{KeywordMap, Token, TokenType, KEYWORDS};
+use super::lexer::{Token, TokenType, Type};
 use crate::error::{ErrorType::ParseError, VeloError, ERROR_INDICATOR};
 
-use std::process;
-
+/// A recursive descent parser over the tokens produced by the lexer. The tokens
+/// are never modified, every rule only moves the cursor forward, so rules can
+/// freely be started from inside one another
 #[derive(Debug)]
-pub struct Parser {
-    pub tokens: Vec<Token>,
+pub struct Parser<'a> {
+    tokens: &'a [Token],
     pub cursor: usize,
-    pub nodes: Vec<Ast>,
     pub errors: Vec<VeloError>,
+    /// Type parameters of the generic functions currently being parsed
+    type_params: Vec<String>,
+    /// How many functions the cursor is currently inside of
+    function_depth: usize,
+    /// How many loops the cursor is inside of, within the current function
+    loop_depth: usize,
+    /// Types declared with `type Name = T`
+    type_aliases: HashMap<String, Type>,
+    /// Set when a `>>` closed two nested `map<K, V>` types at once
+    pending_gt: bool,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [Token]) -> Parser<'a> {
         Parser {
             tokens,
             cursor: 0,
-            nodes: Vec::new(),
             errors: Vec::new(),
+            type_params: Vec::new(),
+            function_depth: 0,
+            loop_depth: 0,
+            type_aliases: HashMap::new(),
+            pending_gt: false,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Ast>, VeloError> {
-        while !self.tokens.is_empty() {
-            match self.tokens[0].token_type {
-                TokenType::Immut => {
-                    self.variable_assignment(false, None, true, false);
-                }
-                TokenType::Function => self.function_declaration(),
-                TokenType::Identifier => {
-                    match self.tokens[1].token_type {
-                        TokenType::ColonEq => {
-                            self.variable_assignment(false, None, false, false);
-                        }
-                        TokenType::LParen => {
-                            self.call_expr();
-                        }
-                        TokenType::Eq => unimplemented!(), // for not reassignment
-                        _ => unimplemented!(),
-                    }
-                }
+    /// Parses every statement, continuing past errors so that all of them can
+    /// be reported at once
+    pub fn parse(&mut self) -> Result<Vec<Ast>, Vec<VeloError>> {
+        let mut nodes = Vec::new();
+
+        while !self.is_at_end() {
+            let errors = self.errors.len();
+            let start = self.cursor;
+
+            match self.parse_statement() {
+                Some(Statement::ExprStmt(expr)) => nodes.push(Ast::Expression(expr)),
+                Some(stmt) => nodes.push(Ast::Statement(stmt)),
+                None => {}
+            }
+
+            // statements that already skipped to the end of their own block
+            // (e.g. a function with a broken signature) don't need to skip further
+            if self.errors.len() > errors && !self.after_boundary() {
+                self.synchronize();
+            }
+
+            // make sure a statement that failed without consuming anything
+            // can't stall the parser
+            if self.cursor == start {
+                self.advance();
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors.clone());
+        }
+
+        Ok(nodes)
+    }
+
+    /// Parses the statement starting at the cursor, leaving the cursor after it.
+    /// Used both at the top level and inside blocks
+    pub fn parse_statement(&mut self) -> Option<Statement> {
+        let token = self.peek();
+
+        let stmt = match (token.token_type, self.peek_at(1).token_type) {
+            (TokenType::Immut, _)
+            | (TokenType::Identifier, TokenType::ColonEq | TokenType::Colon) => {
+                self.parse_variable_declaration()
+            }
+            (TokenType::Identifier, TokenType::Eq) => self.parse_reassignment(),
+            (TokenType::Identifier, TokenType::LBracket | TokenType::QuestionDot) => {
+                self.parse_index_statement()
+            }
+            (TokenType::Function, TokenType::Identifier) => self.parse_function_declaration()?,
+            (TokenType::At, _) => return self.parse_attributed(),
+            (TokenType::Import, _) => self.parse_import()?,
+            (TokenType::Export, _) => self.parse_export()?,
+            (TokenType::For, _) => self.parse_for()?,
+            (TokenType::Loop, _) => self.parse_loop()?,
+            (TokenType::Break | TokenType::Continue, _) if self.loop_depth == 0 => {
+                let message = format!(
+                    "{} \x1b[1mCannot use '{}' outside of a loop\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                self.advance();
+                return None;
+            }
+            (TokenType::Break, _) => Statement::Break {
+                line: self.advance().line_num,
+            },
+            (TokenType::Continue, _) => Statement::Continue {
+                line: self.advance().line_num,
+            },
+            (TokenType::Type, _) => {
+                self.parse_type_alias();
+                return None;
+            }
+            (TokenType::Return, _) if self.function_depth == 0 => {
+                let message = format!(
+                    "{} \x1b[1mCannot use 'return' outside of a function\x1b[0m",
+                    ERROR_INDICATOR
+                );
+                self.throw_error(token.line_num, message);
+                self.advance();
+                return None;
+            }
+            (TokenType::Return, _) => {
+                let line = self.advance().line_num;
+                let value = match self.check(TokenType::Semicolon) || self.check(TokenType::RBrace)
+                {
+                    true => Expression::Null,
+                    false => self.parse_value(),
+                };
+                Statement::Return { value, line }
+            }
+            (TokenType::Semicolon, _) => {
+                self.advance();
+                return None;
+            }
+            // `if`, `try` and blocks at the start of a statement don't need a
+            // `;` after them, so they can't continue into a binary operation
+            (TokenType::If | TokenType::Try, _) => Statement::ExprStmt(self.parse_primary()),
+            // a map literal can still be the value at the end of a block
+            (TokenType::LBrace, _) if self.is_map_literal() => {
+                Statement::ExprStmt(self.parse_value())
+            }
+            (TokenType::LBrace, _) => match self.parse_block("block") {
+                Some(body) => Statement::ExprStmt(Expression::Block(body)),
+                None => return None,
+            },
+            (
+                TokenType::Identifier
+                | TokenType::Function
+                | TokenType::NumericLiteral
+                | TokenType::String
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Null
+                | TokenType::LParen
+                | TokenType::LBracket
+                | TokenType::Sub
+                | TokenType::Not
+                | TokenType::Tilde,
+                _,
+            ) => Statement::ExprStmt(self.parse_value()),
+            _ => {
+                let message = format!(
+                    "{} \x1b[1mUnexpected token '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                self.advance();
+                return None;
+            }
+        };
+
+        Some(stmt)
+    }
+
+    /// Skips ahead to the next likely statement boundary after an error: past
+    /// the next `;`, or up to a `}` or a keyword that starts a statement
+    pub fn synchronize(&mut self) {
+        loop {
+            match self.peek().token_type {
                 TokenType::Semicolon => {
-                    self.tokens.remove(0);
-                }
-                TokenType::EOF => {
-                    self.nodes.push(Ast::Expression(Expression::Null));
-                    self.tokens.remove(0);
+                    self.advance();
+                    return;
                 }
+                TokenType::RBrace
+                | TokenType::EOF
+                | TokenType::Function
+                | TokenType::Immut
+                | TokenType::At
+                | TokenType::Import
+                | TokenType::Export
+                | TokenType::Return
+                | TokenType::If
+                | TokenType::Try
+                | TokenType::For
+                | TokenType::Loop
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Struct
+                | TokenType::Enum => return,
                 _ => {
-                    println!(
-                        "incomplete\n{:#?}\n{:#?}",
-                        self.tokens[0].token_type,
-                        self.tokens[0].lexeme.clone()
-                    );
-                    process::exit(1)
+                    self.advance();
                 }
-            };
+            }
         }
+    }
 
-        let mut ast_nodes = Vec::new();
-
-        for node in &self.nodes {
-            ast_nodes.push(node.clone())
+    /// Skips the body of a function whose header could not be parsed, so its
+    /// statements aren't mistaken for top level ones
+    pub fn skip_block(&mut self) {
+        loop {
+            match self.peek().token_type {
+                TokenType::LBrace => break,
+                TokenType::Semicolon | TokenType::RBrace | TokenType::EOF => return,
+                _ => {
+                    self.advance();
+                }
+            }
         }
-        if self.errors.len() > 0 {
-            for error in self.errors.iter() {
-                println!("{}", error.message);
-                println!("  [filename goes here]:{}\n\n", error.line);
-                println!("TODO: Potential Fixes");
 
-                match error.error_type {
-                    ParseError => println!("This error is found to be of type 'ParseError'"),
-                    _ => unreachable!(),
+        let mut depth = 0;
+        loop {
+            match self.advance().token_type {
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
                 }
+                TokenType::EOF => return,
+                _ => {}
             }
-            process::exit(1);
         }
+    }
 
-        Ok(ast_nodes)
+    /// Returns the token at the cursor
+    pub fn peek(&self) -> &'a Token {
+        self.peek_at(0)
     }
 
-    fn parse_literal(&mut self, token: Token, cursor: Option<usize>) -> Expression {
-        match cursor.is_some() {
-            true => self.cursor = cursor.unwrap(),
-            _ => {}
+    /// Returns the token `offset` places after the cursor, or the final EOF
+    /// token when looking past the end
+    pub fn peek_at(&self, offset: usize) -> &'a Token {
+        let tokens = self.tokens;
+        tokens
+            .get(self.cursor + offset)
+            .or(tokens.last())
+            .expect("the lexer always ends the token stream with EOF")
+    }
+
+    /// Returns the token at the cursor and moves past it. The cursor never
+    /// moves past the final EOF token
+    pub fn advance(&mut self) -> &'a Token {
+        let token = self.peek();
+        if self.cursor < self.tokens.len() - 1 {
+            self.cursor += 1;
         }
-        match token.token_type {
-            TokenType::True => Expression::Bool(true),
-            TokenType::False => Expression::Bool(false),
-            TokenType::String => Expression::StringLiteral(token.lexeme.clone()),
-            TokenType::NumericLiteral | TokenType::Identifier => {
-                if self.tokens.get(self.cursor + 1).is_some() {
-                    self.parse_binary()
-                } else {
-                    let message = format!(
-                        "{} \x1b[1mUnexpected EOF when parsing file",
-                        ERROR_INDICATOR
-                    );
-                    self.throw_error(self.tokens[0].line_num, message);
+        token
+    }
 
-                    Expression::Null
-                }
-            }
-            _ => {
-                let message = format!(
-                    "{} \x1b[1mCannot assign items of type {:#?} to variables\x1b[0m",
-                    ERROR_INDICATOR, token.token_type
-                );
-                self.throw_error(self.tokens[0].line_num, message);
-                self.cursor = 0;
+    pub fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
 
-                Expression::Null
+    pub fn is_at_end(&self) -> bool {
+        self.tokens.is_empty() || self.check(TokenType::EOF)
+    }
+
+    /// Consumes a token of type `token_type`, otherwise reports that `expected`
+    /// is missing and leaves the cursor where it is
+    pub fn expect(&mut self, token_type: TokenType, expected: &str) -> Option<&'a Token> {
+        if self.check(token_type) {
+            return Some(self.advance());
+        }
+
+        let token = self.peek();
+        let message = format!(
+            "{} \x1b[1mExpected {}, found '{}'\x1b[0m",
+            ERROR_INDICATOR, expected, token.lexeme
+        );
+        self.throw_error(token.line_num, message);
+        None
+    }
+
+    /// Whether the previous token ended a statement or block
+    fn after_boundary(&self) -> bool {
+        self.cursor > 0
+            && matches!(
+                self.tokens[self.cursor - 1].token_type,
+                TokenType::Semicolon | TokenType::RBrace
+            )
+    }
+
+    /// Checks whether the tokens at the cursor form a (possibly namespaced)
+    /// call, i.e. `name(` or `module::name(`
+    pub fn is_call(&self) -> bool {
+        let mut offset = 0;
+        while self.peek_at(offset).token_type == TokenType::Identifier {
+            match self.peek_at(offset + 1).token_type {
+                TokenType::DoubleColon => offset += 2,
+                TokenType::LParen => return true,
+                _ => return false,
             }
         }
+        false
+    }
+
+    /// Reads `name` or `module::name` starting at the cursor, leaving the cursor
+    /// after the last identifier
+    pub fn qualified_name(&mut self) -> String {
+        let mut name = self.advance().lexeme.clone();
+        while self.check(TokenType::DoubleColon)
+            && self.peek_at(1).token_type == TokenType::Identifier
+        {
+            self.advance();
+            name = format!("{}::{}", name, self.advance().lexeme);
+        }
+        name
+    }
+
+    pub fn throw_error(&mut self, line: usize, message: String) {
+        self.errors
+            .push(VeloError::error(line, &message, ParseError));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::lexer::Lexer;
+
+    fn parse(source: &str) -> Vec<Ast> {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        Parser::new(&tokens)
+            .parse()
+            .unwrap_or_else(|errors| panic!("failed to parse {:?}: {:?}", source, errors))
     }
 
-    fn parse_binary(&mut self) -> Expression {
-        let mut to_eval: Vec<Token> = Vec::new();
+    /// The lines of the errors found parsing `source`
+    fn error_lines(source: &str) -> Vec<usize> {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        match Parser::new(&tokens).parse() {
+            Ok(nodes) => panic!("expected {:?} not to parse, found {:?}", source, nodes),
+            Err(errors) => errors.iter().map(|error| error.line).collect(),
+        }
+    }
 
-        let mut keyword_error = false;
-        let mut keyword_fault = TokenType::Null;
-        let mut current_index = self.cursor + 1;
+    fn assert_parse_error(source: &str, expected: &str) {
+        let tokens = Lexer::new(source).tokenize().tokens;
+        let errors = Parser::new(&tokens).parse().unwrap_err();
+        assert!(
+            errors.iter().any(|error| error.message.contains(expected)),
+            "expected an error containing {:?} in {:?}, found {:?}",
+            expected,
+            source,
+            errors
+        );
+    }
 
-        to_eval.push(self.tokens[current_index - 1].clone());
+    /// The type written in `x: <ty> := 0;`
+    fn declared_type(ty: &str) -> Type {
+        match parse(&format!("x: {} := 0;", ty)).as_slice() {
+            [Ast::Statement(Statement::VariableAssignment { ty: Some(ty), .. })] => ty.clone(),
+            nodes => panic!("expected a typed declaration, found {:?}", nodes),
+        }
+    }
 
-        while let Some(next_token) = self.tokens.get(current_index) {
-            if [
-                TokenType::NumericLiteral,
-                TokenType::Identifier,
-                TokenType::Add,
-                TokenType::Sub,
-                TokenType::Mul,
-                TokenType::Div,
-            ]
-            .contains(&next_token.token_type)
-                || KeywordMap::get(&KEYWORDS, &next_token.lexeme).is_some()
-            {
-                if KEYWORDS.get(&next_token.lexeme).is_some() {
-                    keyword_error = true;
-                    keyword_fault = next_token.token_type;
-                    println!("keyword: {:#?}", KEYWORDS.get(&next_token.lexeme))
+    /// The expression statement `source` with every binary operation in
+    /// parentheses
+    fn grouped(source: &str) -> String {
+        fn group(expr: &Expression) -> String {
+            match expr {
+                Expression::Int(num) => num.to_string(),
+                Expression::Identifier(name) => name.clone(),
+                Expression::BinaryOp { lhs, op, rhs, .. } => match (lhs.as_ref(), rhs.as_ref()) {
+                    (Ast::Expression(lhs), Ast::Expression(rhs)) => {
+                        format!("({} {} {})", group(lhs), op.symbol(), group(rhs))
+                    }
+                    sides => panic!("expected expressions, found {:?}", sides),
+                },
+                Expression::UnaryOp { op, expr } => format!("{}{}", op.symbol(), group(expr)),
+                Expression::Index { target, index, .. } => {
+                    format!("{}[{}]", group(target), group(index))
                 }
-                if next_token.token_type == TokenType::Identifier {
-                    to_eval.push(next_token.clone());
-                    current_index += 1;
-                } else {
-                    to_eval.push(next_token.clone());
-                    current_index += 1;
+                Expression::CallExpr { name, params, .. } => format!("{}({})", name, args(params)),
+                Expression::Call { callee, params, .. } => {
+                    format!("{}({})", group(callee), args(params))
                 }
-            } else {
-                break;
-            }
-        }
-
-        /*
-        match v {
-            // Check if it's a float and return Float if so
-            val if val.is_sign_positive() && val.fract() != 0.0 => {
-                (Expression::Literal(Literal::Float(val)), Some(Type::Float))
-            }
-            // Check if it's a whole number and fits into i16
-            val if val.fract() == 0.0 && (val as i16 as f32 == val) => (
-                Expression::Literal(Literal::Short(val as i16)),
-                Some(Type::Short),
-            ),
-            // Check if it's a whole number and fits into i32
-            val if val.fract() == 0.0 && (val as i32 as f32 == val) => (
-                Expression::Literal(Literal::Int(val as i32)),
-                Some(Type::Int),
-            ),
-            // For values larger than i32 or with decimal parts, use i64 (Large)
-            val if val.fract() == 0.0 && (val as i64 as f32 == val) => (
-                Expression::Literal(Literal::Large(val as i64)),
-                Some(Type::Large),
-            ),
-            _ => (Expression::Literal(Literal::Float(v)), Some(Type::Float)), // todo: throw error
-        }
-        */
-
-        let keyword_error_msg = format!(
-            "{} \x1b[1mExpected ';' after expression, found keyword '{}'\x1b[0m",
-            ERROR_INDICATOR,
-            TokenType::to_string(keyword_fault),
+                expr => panic!("unexpected expression {:?}", expr),
+            }
+        }
+
+        fn args(params: &[Argument]) -> String {
+            let args: Vec<String> = params.iter().map(|arg| group(&arg.value)).collect();
+            args.join(", ")
+        }
+
+        match parse(source).as_slice() {
+            [Ast::Expression(expr)] => group(expr),
+            nodes => panic!("expected one expression, found {:?}", nodes),
+        }
+    }
+
+    #[test]
+    fn any_expression_can_be_called() {
+        assert_eq!(grouped("adder(2)(3);"), "adder(2)(3)");
+        assert_eq!(grouped("fs[0](4) + 1;"), "(fs[0](4) + 1)");
+        assert_eq!(grouped("make()()[1](x, y);"), "make()()[1](x, y)");
+    }
+
+    fn map(key: Type, value: Type) -> Type {
+        Type::Map(Box::new(key), Box::new(value))
+    }
+
+    fn optional(ty: Type) -> Type {
+        Type::Optional(Box::new(ty))
+    }
+
+    #[test]
+    fn every_broken_statement_is_reported_once() {
+        assert_eq!(
+            error_lines("x := ;\ny := 2;\nz := );\nprintln(y);"),
+            vec![1, 3]
         );
+    }
 
-        self.cursor = current_index - 1;
+    #[test]
+    fn errors_inside_a_function_do_not_hide_later_ones() {
+        let source = "fun f() {\n  a := ;\n  b := 1;\n  c := * 2;\n}\nd := ;";
+        assert_eq!(error_lines(source), vec![2, 4, 6]);
+    }
 
-        match keyword_error {
-            false => {
-                let res = Self::parse_expression(to_eval);
-                res
-            }
-            _ => {
-                self.throw_error(self.tokens[1].line_num, keyword_error_msg);
-                return Expression::Float(0.0);
+    #[test]
+    fn body_of_a_broken_signature_is_skipped() {
+        assert_eq!(error_lines("fun f(x) {\n  y := ;\n}\nz := ;"), vec![1, 4]);
+    }
+
+    #[test]
+    fn import_inside_a_block_is_an_error() {
+        assert_eq!(
+            error_lines("fun f() {\n  import \"x.velo\";\n  a := 1;\n}"),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn defaults_and_named_arguments_keep_their_order() {
+        assert_parse_error(
+            "fun f(a int = 1, b int) {}",
+            "Parameter 'b' of function 'f' needs a default value",
+        );
+        assert_parse_error(
+            "fun f(xs ...int = [1]) {}",
+            "Variadic parameter 'xs' of function 'f' can't have a default value",
+        );
+        assert_parse_error(
+            "f(a: 1, 2);",
+            "Positional arguments to 'f' must come before named ones",
+        );
+    }
+
+    #[test]
+    fn allow_takes_known_lints() {
+        assert_parse_error(
+            "@allow(unused)\nx := 1;",
+            "Unknown lint 'unused', expected one of:",
+        );
+    }
+
+    #[test]
+    fn shift_right_closes_nested_maps() {
+        assert_eq!(
+            declared_type("map<string, map<string, int>>"),
+            map(Type::String, map(Type::String, Type::Int))
+        );
+    }
+
+    #[test]
+    fn optional_after_shift_right_is_the_outer_map() {
+        assert_eq!(
+            declared_type("map<string, map<string, int>>?"),
+            optional(map(Type::String, map(Type::String, Type::Int)))
+        );
+    }
+
+    #[test]
+    fn optional_before_closing_is_the_inner_map() {
+        assert_eq!(
+            declared_type("map<string, map<string, int>?>"),
+            map(Type::String, optional(map(Type::String, Type::Int)))
+        );
+        assert_eq!(
+            declared_type("map<string, map<string, int>?>?"),
+            optional(map(Type::String, optional(map(Type::String, Type::Int))))
+        );
+    }
+
+    #[test]
+    fn nested_maps_inside_arrays() {
+        assert_eq!(
+            declared_type("[map<int, map<int, bool>>]?"),
+            optional(Type::Array(Box::new(map(
+                Type::Int,
+                map(Type::Int, Type::Bool)
+            ))))
+        );
+    }
+
+    #[test]
+    fn bare_fun_is_its_own_type() {
+        assert_eq!(declared_type("fun"), Type::AnyFunction);
+        assert_eq!(
+            declared_type("fun() -> void"),
+            Type::Function {
+                params: Vec::new(),
+                ret: Box::new(Type::Void)
             }
+        );
+    }
+
+    #[test]
+    fn unclosed_map_type_is_an_error() {
+        let tokens = Lexer::new("x: map<string, int := 0;").tokenize().tokens;
+        assert!(Parser::new(&tokens).parse().is_err());
+    }
+
+    /// The value of the declaration `x := <value>;`
+    fn declared_value(value: &str) -> Expression {
+        match parse(&format!("x := {};", value)).as_slice() {
+            [Ast::Statement(Statement::VariableAssignment { value, .. })] => value.clone(),
+            nodes => panic!("expected a declaration, found {:?}", nodes),
         }
     }
 
-    fn parse_expression(tokens: Vec<Token>) -> Expression {
-        let mut nums: Vec<Expression> = Vec::new();
-        let mut ops: Vec<TokenType> = Vec::new();
+    #[test]
+    fn block_starting_with_a_typed_declaration() {
+        assert!(matches!(
+            declared_value("{ a: int := 1; a + 1 }"),
+            Expression::Block(_)
+        ));
+        assert!(matches!(
+            declared_value("{ a: map<string, int> := {}; a }"),
+            Expression::Block(_)
+        ));
+        assert!(matches!(
+            declared_value("{ immut a: int := 1; a }"),
+            Expression::Block(_)
+        ));
+    }
 
-        let mut i = 0;
-        while i < tokens.len() {
-            match tokens[i].token_type {
-                TokenType::Add | TokenType::Sub | TokenType::Mul | TokenType::Div => {
-                    ops.push(tokens[i].token_type.clone())
-                }
-                TokenType::Identifier => {
-                    let num = tokens[i].lexeme.clone();
-                    nums.push(Expression::Identifier(num))
-                }
-                _ => {
-                    let num = tokens[i].lexeme.clone().parse::<f32>();
-                    if num.is_ok() {
-                        nums.push(Expression::Float(num.unwrap()))
-                    }
-                }
+    #[test]
+    fn map_with_a_variable_as_key() {
+        assert!(matches!(declared_value("{a: 1}"), Expression::Map(entries) if entries.len() == 1));
+        assert!(matches!(declared_value("{}"), Expression::Map(entries) if entries.is_empty()));
+    }
+
+    #[test]
+    fn map_as_the_value_of_a_block() {
+        let nodes = parse("fun mk() -> map<string, int> { {\"a\": 1} }");
+        let body = match nodes.as_slice() {
+            [Ast::Statement(Statement::Function { body, .. })] => body,
+            nodes => panic!("expected a function, found {:?}", nodes),
+        };
+        assert!(body.stmts.is_empty());
+        assert!(matches!(body.value.as_deref(), Some(Expression::Map(_))));
+
+        match declared_value("if c { {\"a\": 1} } else { {} }") {
+            Expression::If {
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => {
+                assert!(matches!(
+                    then_branch.value.as_deref(),
+                    Some(Expression::Map(_))
+                ));
+                assert!(matches!(
+                    *else_branch,
+                    Expression::Block(body) if matches!(body.value.as_deref(), Some(Expression::Map(_)))
+                ));
             }
-            i += 1;
+            value => panic!("expected an if, found {:?}", value),
         }
+    }
 
-        let mut result_expr = nums.pop().unwrap();
-        while let Some(op) = ops.pop() {
-            let rhs_expr = nums.pop().unwrap();
-            result_expr = Expression::BinaryOp {
-                lhs: Box::new(Ast::Expression(result_expr)),
-                op,
-                rhs: Box::new(Ast::Expression(rhs_expr)),
-            };
-        }
+    #[test]
+    fn bitwise_operators_bind_or_xor_and() {
+        assert_eq!(grouped("a | b ^ c & d;"), "(a | (b ^ (c & d)))");
+        assert_eq!(grouped("a & b ^ c | d;"), "(((a & b) ^ c) | d)");
+    }
 
-        result_expr
+    #[test]
+    fn xor_is_left_associative() {
+        assert_eq!(grouped("a ^ b ^ c;"), "((a ^ b) ^ c)");
     }
 
-    pub fn throw_error(&mut self, line: usize, message: String) {
-        self.errors
-            .push(VeloError::error(line, &message, ParseError));
+    #[test]
+    fn arithmetic_and_shifts_bind_tighter_than_xor() {
+        assert_eq!(grouped("1 + 2 * 3 ^ 4;"), "((1 + (2 * 3)) ^ 4)");
+        assert_eq!(grouped("a ^ b << 2;"), "(a ^ (b << 2))");
+    }
+
+    #[test]
+    fn comparisons_bind_looser_than_xor() {
+        assert_eq!(grouped("a ^ b == c;"), "((a ^ b) == c)");
     }
 }
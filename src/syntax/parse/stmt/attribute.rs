@@ -0,0 +1,49 @@
+use super::super::Parser;
+use crate::error::{Lint, ERROR_INDICATOR};
+use crate::syntax::ast::{Attribute, Statement};
+use crate::syntax::lexer::TokenType;
+
+impl Parser<'_> {
+    /// Parses `@allow(lint, ...)` with the cursor on `@`, followed by the
+    /// statement it applies to
+    pub fn parse_attributed(&mut self) -> Option<Statement> {
+        let line = self.advance().line_num;
+        let name = self.expect(TokenType::Identifier, "attribute name after '@'")?;
+        let name = name.lexeme.clone();
+        if name != "allow" {
+            let message = format!(
+                "{} \x1b[1mUnknown attribute '@{}', expected '@allow'\x1b[0m",
+                ERROR_INDICATOR, name
+            );
+            self.throw_error(line, message);
+            return None;
+        }
+
+        self.expect(TokenType::LParen, "'(' after the attribute name")?;
+        let mut args = Vec::new();
+        while !self.check(TokenType::RParen) && !self.is_at_end() {
+            let arg = self.expect(TokenType::Identifier, "the name of a lint")?;
+            if Lint::from_name(&arg.lexeme).is_none() {
+                let message = format!(
+                    "{} \x1b[1mUnknown lint '{}', expected one of: {}\x1b[0m",
+                    ERROR_INDICATOR,
+                    arg.lexeme,
+                    Lint::names()
+                );
+                self.throw_error(arg.line_num, message);
+            }
+            args.push(arg.lexeme.clone());
+
+            if !self.check(TokenType::RParen) {
+                self.expect(TokenType::Comma, "',' or ')' after the lint")?;
+            }
+        }
+        self.expect(TokenType::RParen, "')' to close the attribute")?;
+
+        let stmt = self.parse_statement()?;
+        Some(Statement::Attributed {
+            attribute: Attribute { name, args },
+            stmt: Box::new(stmt),
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use super::super::Parser;
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::ast::{FunctionBody, Statement};
+use crate::syntax::lexer::TokenType;
+
+impl Parser<'_> {
+    /// Parses `{ stmt; stmt; value }` with the cursor on the opening brace,
+    /// leaving the cursor after the closing brace. `context` names what the
+    /// block belongs to in error messages, e.g. "function body"
+    pub fn parse_block(&mut self, context: &str) -> Option<FunctionBody> {
+        let expected = format!("'{{' to start {}", context);
+        self.expect(TokenType::LBrace, &expected)?;
+
+        let mut stmts = Vec::new();
+        let mut value = None;
+
+        loop {
+            match self.peek().token_type {
+                TokenType::RBrace => {
+                    self.advance();
+                    return Some(FunctionBody::new(stmts, value));
+                }
+                TokenType::EOF => break,
+                TokenType::Import | TokenType::Export => {
+                    let token = self.peek();
+                    let message = format!(
+                        "{} \x1b[1m'{}' is only allowed at the top level of a file\x1b[0m",
+                        ERROR_INDICATOR, token.lexeme
+                    );
+                    self.throw_error(token.line_num, message);
+                    self.advance();
+                    self.synchronize();
+                }
+                _ => {
+                    let errors = self.errors.len();
+                    let start = self.cursor;
+                    let stmt = self.parse_statement();
+
+                    if self.errors.len() > errors {
+                        self.synchronize();
+                        if self.cursor == start {
+                            self.advance();
+                        }
+                        continue;
+                    }
+
+                    match stmt {
+                        Some(Statement::ExprStmt(expr)) if self.check(TokenType::RBrace) => {
+                            value = Some(Box::new(expr))
+                        }
+                        Some(stmt) => stmts.push(stmt),
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        let line = self.peek().line_num;
+        let message = format!(
+            "{} \x1b[1mExpected '}}' to close {}\x1b[0m",
+            ERROR_INDICATOR, context
+        );
+        self.throw_error(line, message);
+        None
+    }
+}
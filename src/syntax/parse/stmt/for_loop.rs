@@ -0,0 +1,48 @@
+use super::super::Parser;
+use crate::syntax::ast::Statement;
+use crate::syntax::lexer::TokenType;
+
+impl Parser<'_> {
+    /// Parses `for name in iterable { body }` or `for key, value in iterable
+    /// { body }` with the cursor on `for`
+    pub fn parse_for(&mut self) -> Option<Statement> {
+        let line = self.advance().line_num;
+        let name = self
+            .expect(TokenType::Identifier, "loop variable name after 'for'")?
+            .lexeme
+            .clone();
+        let value_name = match self.check(TokenType::Comma) {
+            true => {
+                self.advance();
+                let token = self.expect(TokenType::Identifier, "second loop variable name")?;
+                Some(token.lexeme.clone())
+            }
+            false => None,
+        };
+        self.expect(TokenType::In, "'in' after loop variable")?;
+
+        let iterable = self.parse_binary(0);
+        self.loop_depth += 1;
+        let body = self.parse_block("'for' body");
+        self.loop_depth -= 1;
+        let body = body?;
+
+        Some(Statement::For {
+            name,
+            value_name,
+            iterable,
+            body,
+            line,
+        })
+    }
+
+    /// Parses `loop { body }` with the cursor on `loop`
+    pub fn parse_loop(&mut self) -> Option<Statement> {
+        let line = self.advance().line_num;
+        self.loop_depth += 1;
+        let body = self.parse_block("'loop' body");
+        self.loop_depth -= 1;
+
+        Some(Statement::Loop { body: body?, line })
+    }
+}
@@ -1,254 +1,294 @@
 use super::super::Parser;
 use crate::error::ERROR_INDICATOR;
-use crate::syntax::ast::{Ast, Expression, FunctionBody, Statement};
-use crate::syntax::lexer::{Token, TokenType, Type};
-
-impl Parser {
-    pub fn function_declaration(&mut self) {
-        let mut name = self.parse_function_name();
-        let mut params = self.parse_function_params(&name);
-        let mut ret_type = self.parse_function_ret_type(&name);
-
-        let mut stmts = Vec::new();
-        let mut exprs = Vec::new();
-        let mut block = None;
-        // Return type
-
-        // make variable_assignment not drain tokens when parsing for function body
-        // MOST CLEAN CODE OF 2024 (official)
-        if let Some(token) = self.tokens.get(self.cursor - 1) {
-            println!("{}", token.lexeme.clone());
-            if token.token_type == TokenType::LBrace || token.token_type == TokenType::Identifier {
-                let mut brace_count = 1;
-                let mut body_cursor = self.cursor + 1;
-
-                match token.token_type {
-                    TokenType::LBrace => body_cursor = self.cursor,
-                    _ => body_cursor = self.cursor + 1,
-                }
+use crate::syntax::ast::{Expression, Param, Statement};
+use crate::syntax::lexer::{TokenType, Type};
 
-                while let Some(body_token) = self.tokens.get(body_cursor) {
-                    println!("body token {}", body_token.lexeme);
-                    match body_token.token_type {
-                        TokenType::LBrace => brace_count += 1,
-                        TokenType::RBrace => {
-                            brace_count -= 1;
-                            if brace_count == 0 {
-                                self.cursor = body_cursor + 1;
-                                break;
-                            }
-                        }
-                        TokenType::Identifier => match self.tokens[body_cursor + 1].token_type {
-                            TokenType::ColonEq => {
-                                body_cursor += 1;
-                                let var =
-                                    self.variable_assignment(true, Some(body_cursor), false, true);
-                                stmts.push(var.unwrap())
-                            }
-                            _ => unimplemented!(),
-                        },
-
-                        _ => {}
-                    }
+impl Parser<'_> {
+    /// Parses `fun name<T>(params) -> type { body }` with the cursor on `fun`,
+    /// leaving the cursor after the closing brace
+    pub fn parse_function_declaration(&mut self) -> Option<Statement> {
+        let errors = self.errors.len();
+        let line = self.advance().line_num;
+        let name = self.parse_function_name();
+        let type_params = self.parse_type_params(&name);
 
-                    body_cursor += 1;
-                }
-            } else {
-                let message = format!(
-                    "{} \x1b[1mExpected '{{' to start function body, found '{}'\x1b[0m",
-                    ERROR_INDICATOR,
-                    token.lexeme.clone()
-                );
-                self.throw_error(token.line_num, message);
+        // the type parameters are only in scope for this function's signature
+        // and body, including any functions nested inside it
+        let outer_type_params = self.type_params.len();
+        self.type_params.extend(type_params.iter().cloned());
+        let function = self.parse_function_rest(name, type_params, errors, line);
+        self.type_params.truncate(outer_type_params);
 
-                self.tokens.clear();
-                return;
-            }
-        } else {
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing function body\x1b[0m",
-                ERROR_INDICATOR,
-            );
-            self.throw_error(0, message);
+        function
+    }
 
-            return;
+    /// Parses an anonymous function, i.e. `fun(x int) -> int { x * 2 }`
+    pub fn parse_lambda(&mut self) -> Expression {
+        let errors = self.errors.len();
+        let name = "<lambda>".to_string();
+        self.advance();
+        let params = self.parse_function_params(&name);
+        let ret_type = match self.errors.len() > errors {
+            true => Type::Void,
+            false => self.parse_function_ret_type(&name),
+        };
+
+        if self.errors.len() > errors {
+            self.skip_block();
+            return Expression::Null;
         }
 
-        let body = FunctionBody::new(stmts, exprs, block);
+        // a `break` inside a function can't leave a loop around it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.function_depth += 1;
+        let body = self.parse_block("function body");
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+
+        match body {
+            Some(body) => Expression::Lambda {
+                params,
+                body,
+                ret_type,
+            },
+            None => Expression::Null,
+        }
+    }
 
-        self.tokens.drain(0..self.cursor);
-        self.cursor = 0;
-        // Avengers! Assemble (please help)
-        let function_assignment = Statement::Function {
+    /// Parses the parameters, return type and body of a function declaration
+    fn parse_function_rest(
+        &mut self,
+        name: String,
+        type_params: Vec<String>,
+        errors: usize,
+        line: usize,
+    ) -> Option<Statement> {
+        let params = match self.errors.len() > errors {
+            true => Vec::new(),
+            false => self.parse_function_params(&name),
+        };
+        let ret_type = match self.errors.len() > errors {
+            true => Type::Void,
+            false => self.parse_function_ret_type(&name),
+        };
+
+        if self.errors.len() > errors {
+            self.skip_block();
+            return None;
+        }
+        // a `break` inside a function can't leave a loop around it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.function_depth += 1;
+        let body = self.parse_block("function body");
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        let body = body?;
+
+        Some(Statement::Function {
             name,
+            type_params,
             params,
             ret_type,
             body,
-        };
-        self.nodes.push(Ast::Statement(function_assignment));
+            line,
+            end_line: self.tokens[self.cursor - 1].line_num,
+        })
     }
 
-    fn parse_function_name(&mut self) -> String {
-        let mut name = String::new();
+    /// Parses the optional `<T, U>` following a function name
+    fn parse_type_params(&mut self, name: &str) -> Vec<String> {
+        let mut type_params: Vec<String> = Vec::new();
+        if !self.check(TokenType::Lt) {
+            return type_params;
+        }
+        self.advance();
 
-        match self.tokens.get(1) {
-            Some(next_token) if next_token.token_type == TokenType::Identifier => {
-                name = next_token.lexeme.clone();
-                self.cursor += 1;
-            }
-            Some(next_token) => {
+        loop {
+            let token = self.peek();
+            if token.token_type != TokenType::Identifier {
                 let message = format!(
-                    "{} \x1b[1mCannot declare function with name of type {}",
-                    ERROR_INDICATOR,
-                    TokenType::to_string(next_token.token_type)
+                    "{} \x1b[1mExpected type parameter name for function '{}', found '{}'\x1b[0m",
+                    ERROR_INDICATOR, name, token.lexeme
                 );
-                self.throw_error(next_token.line_num, message);
+                self.throw_error(token.line_num, message);
+                return type_params;
             }
-            None => {
+            self.advance();
+
+            if Type::from_string(token.lexeme.clone()).is_some() {
                 let message = format!(
-                    "{} \x1b[1mUnexpected end of input while parsing function declaration\x1b[0m",
-                    ERROR_INDICATOR,
+                    "{} \x1b[1mCannot use built-in type '{}' as a type parameter of function '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme, name
                 );
-                self.throw_error(self.tokens[0].line_num, message);
+                self.throw_error(token.line_num, message);
+            } else if type_params.contains(&token.lexeme) {
+                let message = format!(
+                    "{} \x1b[1mType parameter '{}' of function '{}' is declared twice\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme, name
+                );
+                self.throw_error(token.line_num, message);
+            }
+            type_params.push(token.lexeme.clone());
+
+            if self.check(TokenType::Comma) {
+                self.advance();
+                continue;
             }
+            self.expect(TokenType::Gt, "',' or '>' after type parameters");
+            return type_params;
         }
+    }
 
-        name
+    /// Reads the function name at the cursor
+    fn parse_function_name(&mut self) -> String {
+        let token = self.peek();
+        if token.token_type == TokenType::Identifier {
+            self.advance();
+            return token.lexeme.clone();
+        }
+
+        let message = format!(
+            "{} \x1b[1mCannot declare function with name of type {}\x1b[0m",
+            ERROR_INDICATOR,
+            TokenType::to_string(token.token_type)
+        );
+        self.throw_error(token.line_num, message);
+        String::new()
     }
 
-    fn parse_function_params(&mut self, name: &String) -> Vec<(String, Type)> {
+    /// Parses `(name type, name type = default, name ...type)` with the cursor on the opening paren,
+    /// leaving the cursor after the closing paren
+    fn parse_function_params(&mut self, name: &str) -> Vec<Param> {
         let mut params = Vec::new();
 
-        if let Some(token) = self.tokens.get(self.cursor + 1) {
-            if token.token_type == TokenType::LParen {
-                let mut param_cursor = self.cursor + 2;
-                while let Some(param_token) = self.tokens.get(param_cursor) {
-                    if param_token.token_type == TokenType::RParen {
-                        self.cursor = param_cursor + 1;
-                        break;
-                    } else if param_token.token_type == TokenType::Identifier {
-                        // Parsing parameter name
-                        let param_name = param_token.lexeme.clone();
-
-                        if let Some(next_token) = self.tokens.get(param_cursor + 1) {
-                            // Checking for parameter type declaration
-                            if next_token.token_type == TokenType::Identifier {
-                                if let Some(type_token) = self.tokens.get(param_cursor + 1) {
-                                    // Parsing parameter type
-                                    let param_type = Type::from_string(type_token.lexeme.clone());
-                                    params.push((param_name, param_type));
-
-                                    param_cursor += 2; // Move cursor past parameter type
-                                    if let Some(next_next_token) = self.tokens.get(param_cursor) {
-                                        match next_next_token.token_type {
-                                            TokenType::Comma => param_cursor += 1,
-                                            TokenType::RParen => {
-                                                self.cursor = param_cursor + 1;
-                                                break;
-                                            }
-                                            _ => {
-                                                // Handle unexpected token
-                                                let message = format!(
-                                                "{} \x1b[1mUnexpected token '{:#?}' while parsing parameters for function '{}'\x1b[0m",
-                                                ERROR_INDICATOR, next_next_token.token_type, name
-                                            );
-                                                self.throw_error(next_next_token.line_num, message);
-                                                self.tokens.drain(0..param_cursor);
-                                                break;
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    // Handle missing parameter type
-                                    let message = format!(
-                                        "{} \x1b[1mExpected parameter type after '{}'\x1b[0m",
-                                        ERROR_INDICATOR, param_name
-                                    );
-                                    self.throw_error(next_token.line_num, message);
-                                    self.tokens.clear();
-                                    self.cursor += 1;
-                                    break;
-                                }
-                            } else {
-                                let message = format!(
-                                    "{} \x1b[1mExpected type to follow parameter, but found '{}' for function '{}'",
-                                    ERROR_INDICATOR,
-                                    next_token.lexeme.clone(),
-                                    name,
-                                );
-                                self.throw_error(next_token.line_num, message);
-                                self.cursor += 1;
-                                break;
-                            }
+        let token = self.peek();
+        if token.token_type != TokenType::LParen {
+            let message = format!(
+                "{} \x1b[1mExpected '(' after function name, found '{}' for function '{}'\x1b[0m",
+                ERROR_INDICATOR, token.lexeme, name
+            );
+            self.throw_error(token.line_num, message);
+            self.advance();
+            return params;
+        }
+        self.advance();
+
+        loop {
+            let param_token = self.peek();
+            match param_token.token_type {
+                TokenType::RParen => {
+                    self.advance();
+                    break;
+                }
+                TokenType::Identifier => {
+                    // Parsing parameter name and type
+                    self.advance();
+                    let errors = self.errors.len();
+                    let variadic = self.check(TokenType::Ellipsis);
+                    if variadic {
+                        self.advance();
+                    }
+                    let ty = self.parse_type();
+                    let default = match self.check(TokenType::Eq) {
+                        true => {
+                            self.advance();
+                            Some(self.parse_binary(0))
                         }
-                    } else {
-                        // Handle unexpected token for parameter name
+                        false => None,
+                    };
+
+                    if self.errors.len() > errors {
+                        break;
+                    }
+
+                    // defaults can only be left out from the end of a
+                    // positional call
+                    let after_default = params.last().is_some_and(|p: &Param| p.default.is_some());
+                    if after_default && default.is_none() {
                         let message = format!(
-                        "{} \x1b[1mUnexpected token '{:#?}' while parsing parameters for function '{}'\x1b[0m",
-                        ERROR_INDICATOR, TokenType::to_string(param_token.token_type), name
-                    );
+                            "{} \x1b[1mParameter '{}' of function '{}' needs a default value since it follows a parameter with one\x1b[0m",
+                            ERROR_INDICATOR, param_token.lexeme, name
+                        );
+                        self.throw_error(param_token.line_num, message);
+                    }
+                    if variadic && default.is_some() {
+                        let message = format!(
+                            "{} \x1b[1mVariadic parameter '{}' of function '{}' can't have a default value\x1b[0m",
+                            ERROR_INDICATOR, param_token.lexeme, name
+                        );
+                        self.throw_error(param_token.line_num, message);
+                    }
+                    if params.last().is_some_and(|p: &Param| p.variadic) {
+                        let message = format!(
+                            "{} \x1b[1mParameter '{}' of function '{}' comes after a variadic parameter, which has to be last\x1b[0m",
+                            ERROR_INDICATOR, param_token.lexeme, name
+                        );
+                        self.throw_error(param_token.line_num, message);
+                    }
+                    if params.iter().any(|p| p.name == param_token.lexeme) {
+                        let message = format!(
+                            "{} \x1b[1mParameter '{}' of function '{}' is declared twice\x1b[0m",
+                            ERROR_INDICATOR, param_token.lexeme, name
+                        );
                         self.throw_error(param_token.line_num, message);
-                        self.tokens.clear();
-                        break;
+                    }
+
+                    params.push(Param {
+                        name: param_token.lexeme.clone(),
+                        ty,
+                        default,
+                        variadic,
+                    });
+
+                    let token = self.peek();
+                    match token.token_type {
+                        TokenType::Comma => {
+                            self.advance();
+                        }
+                        TokenType::RParen => {}
+                        _ => {
+                            let message = format!(
+                                "{} \x1b[1mUnexpected token '{}' while parsing parameters for function '{}'\x1b[0m",
+                                ERROR_INDICATOR, token.lexeme, name
+                            );
+                            self.throw_error(token.line_num, message);
+                            break;
+                        }
                     }
                 }
-            } else {
-                // Handle missing '(' after function name
-                let message = format!(
-                    "{} \x1b[1mExpected '(' after function name, found '{:#?}' for function '{}'\x1b[0m",
-                    ERROR_INDICATOR, 
-                    TokenType::to_string(token.token_type),
-                    name
-                );
-                self.throw_error(token.line_num, message);
-                self.tokens.clear();
+                _ => {
+                    // Handle unexpected token for parameter name
+                    let message = format!(
+                        "{} \x1b[1mUnexpected token '{}' while parsing parameters for function '{}'\x1b[0m",
+                        ERROR_INDICATOR, param_token.lexeme, name
+                    );
+                    self.throw_error(param_token.line_num, message);
+                    break;
+                }
             }
-        } else {
-            // Handle unexpected end of tokens while parsing function parameters
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing parameters for function '{}'\x1b[0m",
-                ERROR_INDICATOR, name
-            );
-            self.throw_error(0, message);
         }
+
         params
     }
 
-    fn parse_function_ret_type(&mut self, name: &String) -> Type {
-        let mut ret_type = Type::Void;
-
-        if let Some(token) = self.tokens.get(self.cursor) {
-            if token.token_type == TokenType::Gt {
-                if let Some(next_token) = self.tokens.get(self.cursor + 1) {
-                    ret_type = Type::from_string(next_token.lexeme.clone());
-                    self.cursor += 2;
-                } else {
-                    let message = format!(
-                        "{} \x1b[1mExpected return type after '>' for function '{}'\x1b[0m",
-                        ERROR_INDICATOR, name
-                    );
-                    self.throw_error(token.line_num, message);
-                    self.tokens.clear();
-                }
-            } else {
-                match token.token_type {
-                    TokenType::LBrace => {
-                        self.cursor += 1;
-                    }
-                    _ => {
-                        let message = format!("{} \x1b[1mExpected either '>' or '{}' when parsing function '{}', but found {}", ERROR_INDICATOR, "{",  name, token.lexeme.clone());
-                        self.throw_error(token.line_num, message);
-                        self.tokens.clear();
-                    }
-                }
+    /// Parses `-> type` (or the older `> type`), or nothing when the body
+    /// follows right away, leaving the cursor on the opening brace of the body
+    fn parse_function_ret_type(&mut self, name: &str) -> Type {
+        let token = self.peek();
+        match token.token_type {
+            TokenType::Arrow | TokenType::Gt => {
+                self.advance();
+                self.parse_type()
+            }
+            TokenType::LBrace => Type::Void,
+            _ => {
+                let message = format!(
+                    "{} \x1b[1mExpected either '->' or '{{' when parsing function '{}', but found '{}'\x1b[0m",
+                    ERROR_INDICATOR, name, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                Type::Void
             }
-        } else {
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing return type for function '{}'\x1b[0m",
-                ERROR_INDICATOR, name
-            );
-            self.throw_error(0, message);
         }
-        ret_type
     }
 }
@@ -0,0 +1,66 @@
+use super::super::Parser;
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::ast::Statement;
+use crate::syntax::lexer::TokenType;
+
+impl Parser<'_> {
+    /// Parses `import "path.velo"` or `import std::math` with the cursor on
+    /// `import`
+    pub fn parse_import(&mut self) -> Option<Statement> {
+        let line = self.advance().line_num;
+
+        let token = self.peek();
+        match token.token_type {
+            // import "utils.velo";
+            TokenType::String => {
+                self.advance();
+                Some(Statement::ImportPath {
+                    path: token.lexeme.clone(),
+                    is_library: false,
+                    line,
+                })
+            }
+            // import std::math;
+            TokenType::Identifier => Some(Statement::ImportPath {
+                path: self.qualified_name(),
+                is_library: true,
+                line,
+            }),
+            _ => {
+                let message = format!(
+                    "{} \x1b[1mExpected module path after 'import', found '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                None
+            }
+        }
+    }
+
+    /// Parses `export <declaration>` with the cursor on `export`
+    pub fn parse_export(&mut self) -> Option<Statement> {
+        let line = self.advance().line_num;
+
+        let exportable = match self.peek().token_type {
+            TokenType::Function | TokenType::Immut => true,
+            TokenType::Identifier => matches!(
+                self.peek_at(1).token_type,
+                TokenType::ColonEq | TokenType::Colon
+            ),
+            _ => false,
+        };
+
+        if !exportable {
+            let message = format!(
+                "{} \x1b[1mExpected a function or variable declaration after 'export', found '{}'\x1b[0m",
+                ERROR_INDICATOR,
+                self.peek().lexeme
+            );
+            self.throw_error(line, message);
+            return None;
+        }
+
+        let stmt = self.parse_statement()?;
+        Some(Statement::Export(Box::new(stmt)))
+    }
+}
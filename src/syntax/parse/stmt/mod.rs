@@ -1,2 +1,6 @@
+pub mod attribute;
+pub mod block;
+pub mod for_loop;
 pub mod function;
+pub mod import;
 pub mod variable;
@@ -1,90 +1,130 @@
 use super::super::Parser;
 use crate::error::ERROR_INDICATOR;
-use crate::syntax::ast::{Ast, Expression, Statement};
-use crate::syntax::lexer::{Token, TokenType, Type};
+use crate::syntax::ast::{Expression, Statement};
+use crate::syntax::lexer::TokenType;
 
-impl Parser {
-    pub fn variable_assignment(
-        &mut self,
-        ret: bool,
-        cursor: Option<usize>,
-        mk_const: bool,
-        in_fn: bool,
-    ) -> Option<Statement> {
-        let mut name = self.parse_var_name(mk_const, in_fn);
+impl Parser<'_> {
+    /// Parses `name := value`, `name: type := value` or `immut name := value`
+    /// starting at the cursor
+    pub fn parse_variable_declaration(&mut self) -> Statement {
+        let constant = self.check(TokenType::Immut);
+        if constant {
+            self.advance();
+        }
 
-        let mut infer_type = true;
+        let line = self.peek().line_num;
+        let name = self.parse_var_name();
 
-        if cursor.is_some() {
-            self.cursor = cursor.unwrap();
-        } else {
-            self.cursor = self.cursor;
-        }
+        let ty = match self.check(TokenType::Colon) {
+            true => {
+                self.advance();
+                Some(self.parse_type())
+            }
+            false => None,
+        };
+
+        self.expect_assignment(&name, TokenType::ColonEq);
+        let value = self.parse_value();
 
-        self.cursor += 2; // Move cursor past '=' and literal_index
+        Statement::VariableAssignment {
+            constant,
+            name,
+            ty,
+            value,
+            line,
+        }
+    }
 
-        let mut final_type = Type::Void;
+    /// Parses `name = value` starting at the cursor
+    pub fn parse_reassignment(&mut self) -> Statement {
+        let line = self.peek().line_num;
+        let name = self.parse_var_name();
+        self.expect_assignment(&name, TokenType::Eq);
+        let value = self.parse_value();
 
-        //FIX ME: parse_literal panics when type for tuple is invalid
-        //i.e: x: (this)
-        //above panics ^^^
-        let mut value = Expression::Null;
-        let tok = match in_fn {
-            true => self.tokens[self.cursor - 1].clone(),
-            _ => self.tokens[self.cursor].clone(),
-        };
+        Statement::Reassignment { name, value, line }
+    }
 
-        if in_fn {
-            self.cursor -= 1
+    /// Parses a statement starting with `name[`, which is either an assignment
+    /// to an index, i.e. `m["a"] = 1`, or an expression like `xs[0];`
+    pub fn parse_index_statement(&mut self) -> Statement {
+        let line = self.peek().line_num;
+        let target = self.parse_value();
+        if !self.check(TokenType::Eq) {
+            return Statement::ExprStmt(target);
         }
+        self.advance();
 
-        if let Some(next_token) = self.tokens.get(self.cursor) {
-            match next_token.token_type {
-                TokenType::Identifier => {
-                    if let Some(next_next_token) = self.tokens.get(self.cursor + 1) {
-                        match next_next_token.token_type {
-                            TokenType::LParen => value = self.call_expr_as_var(),
-                            _ => value = self.parse_literal(tok, Some(self.cursor)),
-                        }
-                    }
+        // `a[0][1]` is nested as Index(Index(a, 0), 1)
+        let mut indices = Vec::new();
+        let mut target = target;
+        let name = loop {
+            match target {
+                Expression::Index {
+                    target: inner,
+                    index,
+                    safe: false,
+                    ..
+                } => {
+                    indices.insert(0, *index);
+                    target = *inner;
+                }
+                Expression::Identifier(name) => break name,
+                Expression::Index { safe: true, .. } => {
+                    let message = format!(
+                        "{} \x1b[1mCannot assign through '?.', check the value for null first\x1b[0m",
+                        ERROR_INDICATOR
+                    );
+                    self.throw_error(line, message);
+                    break String::new();
+                }
+                _ => {
+                    let message = format!(
+                        "{} \x1b[1mCan only assign to an index of a variable\x1b[0m",
+                        ERROR_INDICATOR
+                    );
+                    self.throw_error(line, message);
+                    break String::new();
                 }
-                _ => value = self.parse_literal(tok, Some(self.cursor)),
             }
-        }
-        let message = format!(
-            "{} \x1b[1mExpected semicolon following variable '{}', found {}\x1b[0m",
-            ERROR_INDICATOR,
-            name,
-            self.tokens[self.cursor].lexeme.clone()
-        );
+        };
 
-        let variable = Statement::VariableAssignment {
-            constant: mk_const,
+        let value = self.parse_value();
+        Statement::IndexAssignment {
             name,
+            indices,
             value,
-        };
-
-        let mut res = None;
-
-        match ret {
-            false => self.nodes.push(Ast::Statement(variable)),
-            true => res = Some(variable),
-        }
-        if !in_fn {
-            self.tokens.drain(0..=self.cursor); // Adjusted token removal range
-            self.cursor = 0;
+            line,
         }
+    }
 
-        res
+    /// Reads the variable name at the cursor
+    pub fn parse_var_name(&mut self) -> String {
+        match self.expect(TokenType::Identifier, "variable name") {
+            Some(token) => token.lexeme.clone(),
+            None => {
+                self.advance();
+                String::new()
+            }
+        }
     }
-    pub fn parse_var_name(&mut self, is_const: bool, in_fn: bool) -> String {
-        if !is_const {
-            let name = self.tokens[self.cursor].lexeme.clone();
-            name
-        } else {
-            let name = self.tokens[1].lexeme.clone(); // todo handle case where no name
-            self.cursor += 1;
-            name
+
+    /// Skips over the assignment operator following a variable, leaving the
+    /// cursor on the start of the value
+    fn expect_assignment(&mut self, name: &str, operator: TokenType) {
+        let token = self.advance();
+        if token.token_type != operator {
+            let message = format!(
+                "{} \x1b[1mExpected '{}' after variable '{}', found '{}'\x1b[0m",
+                ERROR_INDICATOR,
+                match operator {
+                    TokenType::Eq => "=",
+                    _ => ":=",
+                },
+                name,
+                token.lexeme
+            );
+            self.throw_error(token.line_num, message);
         }
     }
 }
@@ -0,0 +1,204 @@
+use super::Parser;
+use crate::error::ERROR_INDICATOR;
+use crate::syntax::lexer::{TokenType, Type};
+
+impl Parser<'_> {
+    /// Parses a type at the cursor, leaving the cursor after it. Types are
+    /// either names (`int`, an alias or a type parameter), arrays `[T]`,
+    /// maps `map<K, V>`, tuples `(T, U)` or functions `fun(T, U) -> R`, any of
+    /// which can be made optional with a trailing `?`
+    pub fn parse_type(&mut self) -> Type {
+        let ty = self.parse_non_optional_type();
+
+        // after the `>>` of `map<K, map<K, V>>?` the `?` comes after the outer
+        // map, which still has to take its pending `>`
+        if self.pending_gt {
+            return ty;
+        }
+
+        let token = self.peek();
+        match token.token_type {
+            // an alias can already be optional, `T??` means nothing more
+            TokenType::Question if ty.is_optional() => {
+                self.advance();
+                ty
+            }
+            TokenType::Question => {
+                self.advance();
+                Type::Optional(Box::new(ty))
+            }
+            TokenType::QuestionQuestion => {
+                let message = format!(
+                    "{} \x1b[1mType '{}?' is already optional\x1b[0m",
+                    ERROR_INDICATOR, ty
+                );
+                self.throw_error(token.line_num, message);
+                self.advance();
+                Type::Optional(Box::new(ty))
+            }
+            _ => ty,
+        }
+    }
+
+    fn parse_non_optional_type(&mut self) -> Type {
+        let token = self.peek();
+        match token.token_type {
+            TokenType::Identifier if token.lexeme == "map" => {
+                self.advance();
+                self.parse_map_type()
+            }
+            TokenType::Identifier => {
+                self.advance();
+                if let Some(ty) = Type::from_string(token.lexeme.clone()) {
+                    return ty;
+                }
+                if let Some(ty) = self.type_aliases.get(&token.lexeme) {
+                    return ty.clone();
+                }
+                if self.type_params.contains(&token.lexeme) {
+                    return Type::Param(token.lexeme.clone());
+                }
+
+                let message = format!(
+                    "{} \x1b[1mUnknown type '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                Type::Void
+            }
+            TokenType::Function => {
+                self.advance();
+                // a bare `fun` accepts any function
+                if !self.check(TokenType::LParen) {
+                    return Type::AnyFunction;
+                }
+
+                let params = self.parse_type_list("function type");
+                let ret = match self.peek().token_type {
+                    TokenType::Arrow | TokenType::Gt => {
+                        self.advance();
+                        self.parse_type()
+                    }
+                    _ => Type::Void,
+                };
+                Type::Function {
+                    params,
+                    ret: Box::new(ret),
+                }
+            }
+            TokenType::LBracket => {
+                self.advance();
+                let ty = self.parse_type();
+                self.expect(TokenType::RBracket, "']' to close array type");
+                Type::Array(Box::new(ty))
+            }
+            TokenType::LParen => {
+                let mut types = self.parse_type_list("tuple type");
+                match types.len() {
+                    // `(T)` is just T
+                    1 => types.remove(0),
+                    _ => Type::Tuple(types),
+                }
+            }
+            _ => {
+                let message = format!(
+                    "{} \x1b[1mExpected type, found '{}'\x1b[0m",
+                    ERROR_INDICATOR, token.lexeme
+                );
+                self.throw_error(token.line_num, message);
+                Type::Void
+            }
+        }
+    }
+
+    /// Parses the `<K, V>` after `map`
+    fn parse_map_type(&mut self) -> Type {
+        let void = Type::Map(Box::new(Type::Void), Box::new(Type::Void));
+        if self.expect(TokenType::Lt, "'<' after 'map'").is_none() {
+            return void;
+        }
+
+        let errors = self.errors.len();
+        let key = self.parse_type();
+        if self.errors.len() > errors
+            || self
+                .expect(TokenType::Comma, "',' between map key and value types")
+                .is_none()
+        {
+            return void;
+        }
+        let value = self.parse_type();
+        if self.errors.len() > errors {
+            return void;
+        }
+
+        // the `>>` closing `map<K, map<K, V>>` is lexed as a single token, the
+        // inner map consumes it and leaves the second `>` pending for this one
+        if self.pending_gt {
+            self.pending_gt = false;
+        } else if self.check(TokenType::ShiftRight) {
+            self.advance();
+            self.pending_gt = true;
+        } else {
+            self.expect(TokenType::Gt, "'>' to close map type");
+        }
+
+        Type::Map(Box::new(key), Box::new(value))
+    }
+
+    /// Parses `(T, U, ...)` with the cursor on the opening paren
+    fn parse_type_list(&mut self, context: &str) -> Vec<Type> {
+        let mut types = Vec::new();
+        self.advance();
+
+        while !self.check(TokenType::RParen) {
+            let errors = self.errors.len();
+            types.push(self.parse_type());
+            if self.errors.len() > errors {
+                return types;
+            }
+
+            if !self.check(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        let expected = format!("',' or ')' in {}", context);
+        self.expect(TokenType::RParen, &expected);
+        types
+    }
+
+    /// Parses `type Name = T` with the cursor on `type`. Aliases are resolved
+    /// while parsing, so they're usable anywhere after their declaration
+    pub fn parse_type_alias(&mut self) {
+        self.advance();
+
+        let name = match self.expect(TokenType::Identifier, "type alias name") {
+            Some(token) => token,
+            None => return,
+        };
+        if Type::from_string(name.lexeme.clone()).is_some()
+            || self.type_aliases.contains_key(&name.lexeme)
+        {
+            let message = format!(
+                "{} \x1b[1mType '{}' is already defined\x1b[0m",
+                ERROR_INDICATOR, name.lexeme
+            );
+            self.throw_error(name.line_num, message);
+            return;
+        }
+
+        if self
+            .expect(TokenType::Eq, "'=' after type alias name")
+            .is_none()
+        {
+            return;
+        }
+        let errors = self.errors.len();
+        let ty = self.parse_type();
+        if self.errors.len() == errors {
+            self.type_aliases.insert(name.lexeme.clone(), ty);
+        }
+    }
+}